@@ -0,0 +1,139 @@
+use tree_sitter::Node;
+
+/// One `#[...]` attribute, split into its path and whatever follows it.
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    /// Attribute path as written (`test`, `tokio::test`, `cfg`).
+    pub path: String,
+    /// Delimited arguments including the parentheses (`(test)`), if any.
+    pub args: Option<String>,
+    /// Right-hand side of `#[name = value]`, quotes stripped.
+    pub value: Option<String>,
+}
+
+/// The outer attributes (`#[...]`) directly in front of `node`, nearest last.
+pub fn outer_attributes(node: Node, src: &str) -> Vec<Attribute> {
+    let mut attrs = Vec::new();
+
+    let mut cur = node.prev_sibling();
+    while let Some(item) = cur {
+        match item.kind() {
+            "attribute_item" => attrs.extend(item.named_child(0).and_then(|a| parse(a, src))),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        cur = item.prev_sibling();
+    }
+
+    attrs.reverse();
+    attrs
+}
+
+/// The inner attributes (`#![...]`) among the direct children of `node`.
+pub fn inner_attributes(node: Node, src: &str) -> Vec<Attribute> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|c| c.kind() == "inner_attribute_item")
+        .filter_map(|c| c.named_child(0).and_then(|a| parse(a, src)))
+        .collect()
+}
+
+/// Whether `attrs` contain `#[cfg(test)]` (also inside `all(...)`), making the
+/// item exist only in test builds.
+pub fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let Some(args) = attr.args.as_deref().filter(|_| attr.path == "cfg") else {
+            return false;
+        };
+        let args: String = args.chars().filter(|c| !c.is_whitespace()).collect();
+        args == "(test)"
+            || (args.starts_with("(all(")
+                && args.split(['(', ')', ',']).any(|token| token == "test")
+                && !args.contains("not(test"))
+    })
+}
+
+/// Trait paths listed in `#[derive(...)]` attributes, in source order.
+pub fn derives(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path == "derive")
+        .filter_map(|attr| attr.args.as_deref())
+        .flat_map(|args| args.trim_matches(['(', ')']).split(','))
+        .map(|path| path.split_whitespace().collect::<String>())
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+/// Arguments of the `#[repr(...)]` attributes, parentheses stripped and
+/// joined (`C, align(8)`).
+pub fn repr(attrs: &[Attribute]) -> Option<String> {
+    let args: Vec<&str> = attrs
+        .iter()
+        .filter(|attr| attr.path == "repr")
+        .filter_map(|attr| attr.args.as_deref())
+        .map(|args| args.strip_prefix('(').unwrap_or(args))
+        .map(|args| args.strip_suffix(')').unwrap_or(args))
+        .collect();
+    (!args.is_empty()).then(|| args.join(", "))
+}
+
+fn parse(attribute: Node, src: &str) -> Option<Attribute> {
+    let bytes = src.as_bytes();
+    let text = |field: &str| {
+        attribute
+            .child_by_field_name(field)
+            .and_then(|n| n.utf8_text(bytes).ok())
+    };
+
+    Some(Attribute {
+        path: attribute.named_child(0)?.utf8_text(bytes).ok()?.to_string(),
+        args: text("arguments").map(str::to_string),
+        value: text("value").map(|v| v.trim_matches('"').to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    /// The outer attributes of the last item in `src`.
+    fn attributes_of_last_item(src: &str) -> Vec<Attribute> {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(src, None).unwrap();
+        let root = tree.root_node();
+        let item = root.named_children(&mut root.walk()).last().unwrap();
+        outer_attributes(item, src)
+    }
+
+    #[test]
+    fn splits_path_arguments_and_value() {
+        let attrs = attributes_of_last_item(
+            "#[tokio::test(flavor = \"multi_thread\")]\n// comment\n#[path = \"x.rs\"]\nmod x;",
+        );
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs[0].path, "tokio::test");
+        assert_eq!(
+            attrs[0].args.as_deref(),
+            Some("(flavor = \"multi_thread\")")
+        );
+        assert_eq!(attrs[1].path, "path");
+        assert_eq!(attrs[1].value.as_deref(), Some("x.rs"));
+    }
+
+    #[test]
+    fn recognises_cfg_test() {
+        let cfg_test =
+            |attr: &str| is_cfg_test(&attributes_of_last_item(&format!("{attr} mod t {{}}")));
+        assert!(cfg_test("#[cfg(test)]"));
+        assert!(cfg_test("#[cfg( test )]"));
+        assert!(cfg_test("#[cfg(all(test, feature = \"slow\"))]"));
+        assert!(!cfg_test("#[cfg(not(test))]"));
+        assert!(!cfg_test("#[cfg(all(unix, not(test)))]"));
+        assert!(!cfg_test("#[cfg(any(test, unix))]"));
+        assert!(!cfg_test("#[cfg(feature = \"test\")]"));
+        assert!(!cfg_test("#[cfg_attr(test, derive(Debug))]"));
+    }
+}
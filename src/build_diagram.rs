@@ -1,23 +1,66 @@
 use anyhow::Result;
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     env, fs,
     path::{Path, PathBuf},
 };
 
-use crate::parse::{Class, FunctionInfo, ParsedProject};
+use crate::parse::{
+    Class, Constant, CrateSource, DEFAULT_TEST_ATTRIBUTES, FunctionInfo, ParsedProject, TestInfo,
+    Visibility,
+};
+use crate::resolve::{CRATE_ROOT, short_name};
+use crate::workspace::{Member, package_name};
+
+/// Which trait edges (`impl Trait for Type`, supertraits) to draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum TraitImplMode {
+    /// Only traits defined in the scanned sources.
+    #[default]
+    Local,
+    /// Also std/external traits, drawn as `<<external>>` ghost nodes.
+    All,
+}
 
 /// Options controlling how the Mermaid diagrams are rendered.
 pub struct DiagramConfig<'a> {
     pub main_title: &'a str,
     pub tests_title: &'a str,
+    pub modules_title: &'a str,
+    pub calls_title: &'a str,
+    pub crates_title: &'a str,
     pub layout: &'a str,
     pub theme: &'a str,
     pub elk_node_placement: &'a str,
+    pub trait_impls: TraitImplMode,
+    /// Only render items reachable from outside the crate in the main diagram
+    /// (the crate's public API).
+    pub public_only: bool,
+    /// Drop path prefixes from rendered types (`std::rc::Rc<T>` -> `Rc~T~`).
+    pub abbreviate_types: bool,
+    /// Draw calls that do not resolve to a project function as external nodes.
+    pub external_calls: bool,
+    /// Draw `..>` dependency edges to local types used in method and function
+    /// signatures.
+    pub signature_deps: bool,
+    /// Show derived traits as one `<<derive: ...>>` line in the class box
+    /// instead of realization edges.
+    pub compact_derives: bool,
+    /// List methods of `impl Trait for Type` blocks in the class box.
+    pub trait_methods: bool,
+    /// Note the modules holding `impl` blocks for a type declared elsewhere.
+    pub impl_locations: bool,
+    /// Fan `dyn Trait` fields out to dashed edges to every local implementor.
+    pub dyn_implementors: bool,
+    /// Attribute paths (and `name!` macros) that mark a test function.
+    pub test_attributes: &'a [&'a str],
     /// Path to the Rust source directory to scan.
     pub src_dir: &'a Path,
     /// Directory where the generated Mermaid files will be written.
     pub out_dir: &'a Path,
+    /// Workspace root (directory or `Cargo.toml`); when set, every member
+    /// crate is scanned instead of `src_dir`.
+    pub workspace: Option<&'a Path>,
 }
 
 fn default_manifest_dir() -> PathBuf {
@@ -31,33 +74,155 @@ impl<'a> Default for DiagramConfig<'a> {
         Self {
             main_title: "Project",
             tests_title: "Project Tests",
+            modules_title: "Project Modules",
+            calls_title: "Project Calls",
+            crates_title: "Project Crates",
             layout: "elk",
             theme: "dark",
             elk_node_placement: "BRANDES_KOEPF",
+            trait_impls: TraitImplMode::Local,
+            public_only: false,
+            abbreviate_types: false,
+            external_calls: false,
+            signature_deps: false,
+            compact_derives: false,
+            trait_methods: false,
+            impl_locations: false,
+            dyn_implementors: false,
+            test_attributes: DEFAULT_TEST_ATTRIBUTES,
             src_dir: Path::new("src"),
             out_dir: Path::new("diagrams"),
+            workspace: None,
+        }
+    }
+}
+
+/// Per-diagram rendering switches derived from [`DiagramConfig`].
+#[derive(Clone, Copy)]
+struct RenderOptions {
+    public_only: bool,
+    abbreviate_types: bool,
+    compact_derives: bool,
+    trait_methods: bool,
+    impl_locations: bool,
+}
+
+impl Visibility {
+    /// Mermaid member visibility prefix.
+    fn marker(self) -> char {
+        match self {
+            Visibility::Public => '+',
+            Visibility::Crate => '~',
+            Visibility::Restricted => '#',
+            Visibility::Private => '-',
         }
     }
 }
 
 impl Class {
-    fn render(&self) -> (String, Option<String>) {
+    fn render(&self, opts: RenderOptions) -> (String, Option<String>) {
         let mut s = String::new();
 
-        s.push_str(&format!("        class `{}` {{\n", self.name));
+        let generics = if self.generics.is_empty() {
+            String::new()
+        } else {
+            format!("~{}~", self.generics.join(", "))
+        };
+        s.push_str(&format!(
+            "        class {}{}[\"{}\"] {{\n",
+            mermaid_id(&self.path),
+            generics,
+            self.name
+        ));
 
         if let Some(st) = &self.stereotype {
             s.push_str(&format!("            <<{}>>\n", st));
         }
+        if let Some(repr) = &self.repr {
+            s.push_str(&format!("            <<repr({})>>\n", repr));
+        }
+        if self.non_exhaustive {
+            s.push_str("            <<non_exhaustive>>\n");
+        }
+
+        if opts.compact_derives && !self.derives.is_empty() {
+            let names: Vec<&str> = self.derives.iter().map(|d| short_name(d)).collect();
+            s.push_str(&format!("            <<derive: {}>>\n", names.join(", ")));
+        }
+
+        if opts.impl_locations {
+            let own = self.path.rsplit_once("::").map(|(module, _)| module);
+            let elsewhere: Vec<&str> = self
+                .impl_modules
+                .iter()
+                .map(String::as_str)
+                .filter(|m| Some(*m) != own)
+                .collect();
+            if !elsewhere.is_empty() {
+                s.push_str(&format!(
+                    "            <<impl in {}>>\n",
+                    elsewhere.join(", ")
+                ));
+            }
+        }
 
         s.push_str(&format!("            <<{}>>\n", self.file));
 
-        for f in &self.fields {
-            s.push_str(&format!("            {}\n", f));
+        let shown = |vis: Visibility| !opts.public_only || vis == Visibility::Public;
+        let is_enum = self.stereotype.as_deref() == Some("enum");
+
+        for f in self.fields.iter().filter(|f| shown(f.visibility)) {
+            let marker = f.visibility.marker();
+            match &f.ty {
+                // Tuple fields read `0: Inner`.
+                Some(ty) if f.name.bytes().all(|b| b.is_ascii_digit()) => s.push_str(&format!(
+                    "            {}{}: {}\n",
+                    marker,
+                    f.name,
+                    mermaid_type(ty, opts.abbreviate_types)
+                )),
+                // Variants read `Rect: Point, Point = 2`.
+                _ if is_enum => {
+                    s.push_str(&format!("            {}{}", marker, f.name));
+                    if let Some(ty) = &f.ty {
+                        s.push_str(&format!(": {}", mermaid_type(ty, opts.abbreviate_types)));
+                    }
+                    if let Some(value) = &f.discriminant {
+                        s.push_str(&format!(" = {}", value));
+                    }
+                    s.push('\n');
+                }
+                Some(ty) => s.push_str(&format!(
+                    "            {}{} {}\n",
+                    marker,
+                    mermaid_type(ty, opts.abbreviate_types),
+                    f.name
+                )),
+                None => s.push_str(&format!("            {}{}\n", marker, f.name)),
+            }
         }
 
-        for m in &self.methods {
-            s.push_str(&format!("            {}()\n", m));
+        let methods = self
+            .methods
+            .iter()
+            .filter(|m| opts.trait_methods || m.trait_impl.is_none());
+        for m in methods.filter(|m| shown(m.visibility)) {
+            let sig = format!(
+                "{}{}({})",
+                m.visibility.marker(),
+                m.name,
+                mermaid_params(&m.params, opts.abbreviate_types)
+            );
+            let classifier = if m.is_abstract { "*" } else { "" };
+            match &m.ret {
+                Some(ret) => s.push_str(&format!(
+                    "            {} {}{}\n",
+                    sig,
+                    mermaid_type(ret, opts.abbreviate_types),
+                    classifier
+                )),
+                None => s.push_str(&format!("            {}{}\n", sig, classifier)),
+            }
         }
 
         s.push_str("        }\n");
@@ -65,35 +230,21 @@ impl Class {
         let note = self
             .doc
             .as_ref()
-            .map(|doc| format!("note for `{}` \"{}\"\n", self.name, doc));
+            .map(|doc| format!("note for {} \"{}\"\n", mermaid_id(&self.path), doc));
 
         (s, note)
     }
 }
 
-/// Public entry point: generate both main and test diagrams into the crate root.
+/// Public entry point: generate the main, test, module and call diagrams (plus
+/// the crate graph for a workspace) into the crate root.
 ///
 /// `config` controls the title / layout / theme of the generated Mermaid files.
 pub fn generate_diagrams_with_config(config: &DiagramConfig<'_>) -> Result<()> {
-    let manifest_dir = default_manifest_dir();
-
-    // If src_dir/out_dir in config are empty (from Default), fall back to
-    // manifest-based paths.
-    let src_path_buf;
-    let out_path_buf;
-
-    let src_path: &Path = if config.src_dir.as_os_str().is_empty() {
-        src_path_buf = manifest_dir.join("src");
-        &src_path_buf
-    } else {
-        config.src_dir
-    };
-
-    let out_path: &Path = if config.out_dir.as_os_str().is_empty() {
-        out_path_buf = manifest_dir.clone();
-        &out_path_buf
-    } else {
-        config.out_dir
+    let (src_path, out_path) = resolve_dirs(config);
+    let members = match config.workspace {
+        Some(root) => Some(crate::workspace::load_workspace(root)?),
+        None => None,
     };
 
     // Use parser module
@@ -101,21 +252,34 @@ pub fn generate_diagrams_with_config(config: &DiagramConfig<'_>) -> Result<()> {
         classes,
         file_functions_main,
         file_functions_tests,
-    } = crate::parser::parse_project(src_path)?;
+        file_consts_main,
+        file_consts_tests,
+        modules,
+        module_deps,
+        ..
+    } = parse_sources(config, &src_path, members.as_deref())?;
+
+    // In public-API mode, types outside the public API are dropped from the
+    // main diagram.
+    let hidden = |class: &Class| config.public_only && !is_test_class(class) && !class.exported;
+    let hidden_name = |name: &str| classes.get(name).is_some_and(hidden);
 
     // Group classes by main/test file
     let mut files_main: BTreeMap<String, Vec<&Class>> = BTreeMap::new();
     let mut files_test: BTreeMap<String, Vec<&Class>> = BTreeMap::new();
 
     for class in classes.values() {
-        if is_test_file(&class.file) {
+        if hidden(class) {
+            continue;
+        }
+        if is_test_class(class) {
             files_test
-                .entry(class.file.clone())
+                .entry(namespace(&class.file, &class.inline_module))
                 .or_default()
                 .push(class);
         } else {
             files_main
-                .entry(class.file.clone())
+                .entry(namespace(&class.file, &class.inline_module))
                 .or_default()
                 .push(class);
         }
@@ -127,56 +291,176 @@ pub fn generate_diagrams_with_config(config: &DiagramConfig<'_>) -> Result<()> {
     let mut rendered_edges_main = BTreeSet::new();
     let mut rendered_edges_test = BTreeSet::new();
 
+    let mut external_traits_main = BTreeSet::new();
+    let mut external_traits_test = BTreeSet::new();
+
+    // Local trait -> types implementing it, for `dyn Trait` fan-out.
+    let mut implementors: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for class in classes.values() {
+        for trait_impl in &class.trait_impls {
+            implementors
+                .entry(trait_impl)
+                .or_default()
+                .insert(&class.path);
+        }
+    }
+
     for class in classes.values() {
-        let is_test = is_test_file(&class.file);
-        let edges = if is_test {
-            &mut rendered_edges_test
+        if hidden(class) {
+            continue;
+        }
+        let is_test = is_test_class(class);
+        let (edges, external_traits) = if is_test {
+            (&mut rendered_edges_test, &mut external_traits_test)
         } else {
-            &mut rendered_edges_main
+            (&mut rendered_edges_main, &mut external_traits_main)
         };
+        let public_only = config.public_only && !is_test;
 
+        // Fields of one type pointing at the same target share an edge.
+        let mut merged: BTreeMap<(&str, &str, &str, Option<&str>), Vec<&str>> = BTreeMap::new();
         for rel in &class.relationships {
-            if let Some(label) = &rel.label {
-                edges.insert(format!(
-                    "    {src} {edge} {tgt} : {label}\n",
-                    src = rel.source,
-                    edge = rel.edge_type,
-                    tgt = rel.target,
-                    label = label
-                ));
+            if hidden_name(&rel.target) || (public_only && rel.visibility != Visibility::Public) {
+                continue;
+            }
+            let labels = merged
+                .entry((
+                    &rel.source,
+                    &rel.edge_type,
+                    &rel.target,
+                    rel.multiplicity.as_deref(),
+                ))
+                .or_default();
+            labels.extend(rel.label.as_deref());
+        }
+        for ((source, edge, target, multiplicity), labels) in merged {
+            let multiplicity = multiplicity
+                .map(|m| format!(" \"{m}\""))
+                .unwrap_or_default();
+            let label = if labels.is_empty() {
+                String::new()
             } else {
-                edges.insert(format!(
-                    "    {src} {edge} {tgt}\n",
-                    src = rel.source,
-                    edge = rel.edge_type,
-                    tgt = rel.target
-                ));
+                format!(" : {}", labels.join(", "))
+            };
+            edges.insert(format!(
+                "    {} {}{} {}{}\n",
+                mermaid_id(source),
+                edge,
+                multiplicity,
+                mermaid_id(target),
+                label
+            ));
+        }
+
+        if config.dyn_implementors {
+            for rel in class.relationships.iter().filter(|r| r.trait_object) {
+                if hidden_name(&rel.target) || (public_only && rel.visibility != Visibility::Public)
+                {
+                    continue;
+                }
+                for implementor in implementors.get(rel.target.as_str()).into_iter().flatten() {
+                    if hidden_name(implementor) {
+                        continue;
+                    }
+                    edges.insert(format!(
+                        "    {} ..> {} : dyn {}\n",
+                        mermaid_id(&class.path),
+                        mermaid_id(implementor),
+                        short_name(&rel.target)
+                    ));
+                }
             }
         }
 
+        if config.signature_deps {
+            let methods = class
+                .methods
+                .iter()
+                .filter(|m| config.trait_methods || m.trait_impl.is_none())
+                .filter(|m| !public_only || m.exported);
+            edges.extend(dependency_edges(
+                &mermaid_id(&class.path),
+                methods,
+                |target| {
+                    target == class.path
+                        || hidden_name(target)
+                        || class.relationships.iter().any(|r| r.target == target)
+                },
+            ));
+        }
+
         for trait_impl in &class.trait_impls {
-            edges.insert(format!("    {} <|.. {}\n", trait_impl, class.name));
+            if hidden_name(trait_impl)
+                || (config.compact_derives && class.derives.contains(trait_impl))
+            {
+                continue;
+            }
+            if !classes.contains_key(trait_impl) {
+                if config.trait_impls == TraitImplMode::Local {
+                    continue;
+                }
+                external_traits.insert(trait_impl);
+            }
+            edges.insert(format!(
+                "    {} <|.. {}\n",
+                mermaid_id(trait_impl),
+                mermaid_id(&class.path)
+            ));
+        }
+
+        for supertrait in &class.supertraits {
+            if hidden_name(supertrait) {
+                continue;
+            }
+            if !classes.contains_key(supertrait) {
+                if config.trait_impls == TraitImplMode::Local {
+                    continue;
+                }
+                external_traits.insert(supertrait);
+            }
+            edges.insert(format!(
+                "    {} <|-- {}\n",
+                mermaid_id(supertrait),
+                mermaid_id(&class.path)
+            ));
+        }
+    }
+
+    if config.signature_deps {
+        for (ns, funcs) in by_namespace(&file_functions_main, |f| &f.inline_module) {
+            let funcs = funcs
+                .into_iter()
+                .filter(|f| !config.public_only || f.exported);
+            rendered_edges_main.extend(dependency_edges(
+                &format!("`{}_functions`", ns),
+                funcs,
+                |target| hidden_name(target),
+            ));
         }
     }
 
+    let opts_main = RenderOptions {
+        public_only: config.public_only,
+        abbreviate_types: config.abbreviate_types,
+        compact_derives: config.compact_derives,
+        trait_methods: config.trait_methods,
+        impl_locations: config.impl_locations,
+    };
+    let opts_test = RenderOptions {
+        public_only: false,
+        ..opts_main
+    };
+
     let mut mermaid_main = String::new();
     let mut mermaid_tests = String::new();
 
-    mermaid_main.push_str(&format!(
-        "---\n\
-config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mergeEdges: true\n    nodePlacementStrategy: {elk_node_placement}\n---\n",
-        title = config.main_title,
-        layout = config.layout,
-        theme = config.theme,
-        elk_node_placement = config.elk_node_placement,
-    ));
+    mermaid_main.push_str(&frontmatter(config, config.main_title));
     mermaid_main.push_str("classDiagram\n    direction TB\n");
 
-    for (file_module, class_list) in &files_main {
-        let ns_title = format!("{}.rs", file_module);
-        mermaid_main.push_str(&format!("    namespace `{}` {{\n", ns_title));
+    for (ns, class_list) in &files_main {
+        mermaid_main.push_str(&format!("    namespace `{}` {{\n", namespace_title(ns)));
         for class in class_list {
-            let (class_str, note_opt) = class.render();
+            let (class_str, note_opt) = class.render(opts_main);
             mermaid_main.push_str(&class_str);
             if let Some(note) = note_opt {
                 notes_main.push(note);
@@ -185,7 +469,14 @@ config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mer
         mermaid_main.push_str("    }\n");
     }
 
-    render_functions_namespaces(&mut mermaid_main, &file_functions_main);
+    render_functions_namespaces(
+        &mut mermaid_main,
+        &file_functions_main,
+        "functions",
+        opts_main,
+    );
+    render_consts_namespaces(&mut mermaid_main, &file_consts_main, opts_main);
+    render_external_traits(&mut mermaid_main, &external_traits_main);
 
     for edge in rendered_edges_main {
         mermaid_main.push_str(&edge);
@@ -195,21 +486,13 @@ config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mer
         mermaid_main.push_str(&note);
     }
 
-    mermaid_tests.push_str(&format!(
-        "---\n\
-config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mergeEdges: true\n    nodePlacementStrategy: {elk_node_placement}\n---\n",
-        title = config.tests_title,
-        layout = config.layout,
-        theme = config.theme,
-        elk_node_placement = config.elk_node_placement,
-    ));
+    mermaid_tests.push_str(&frontmatter(config, config.tests_title));
     mermaid_tests.push_str("classDiagram\n    direction TB\n");
 
-    for (file_module, class_list) in &files_test {
-        let ns_title = format!("{}.rs", file_module);
-        mermaid_tests.push_str(&format!("    namespace `{}` {{\n", ns_title));
+    for (ns, class_list) in &files_test {
+        mermaid_tests.push_str(&format!("    namespace `{}` {{\n", namespace_title(ns)));
         for class in class_list {
-            let (class_str, note_opt) = class.render();
+            let (class_str, note_opt) = class.render(opts_test);
             mermaid_tests.push_str(&class_str);
             if let Some(note) = note_opt {
                 notes_test.push(note);
@@ -218,7 +501,20 @@ config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mer
         mermaid_tests.push_str("    }\n");
     }
 
-    render_functions_namespaces(&mut mermaid_tests, &file_functions_tests);
+    render_functions_namespaces(
+        &mut mermaid_tests,
+        &file_functions_tests,
+        "tests",
+        opts_test,
+    );
+    render_consts_namespaces(&mut mermaid_tests, &file_consts_tests, opts_test);
+    render_external_traits(&mut mermaid_tests, &external_traits_test);
+    render_test_links(
+        &mut mermaid_tests,
+        &classes,
+        &file_functions_main,
+        &file_functions_tests,
+    );
 
     for edge in rendered_edges_test {
         mermaid_tests.push_str(&edge);
@@ -228,13 +524,130 @@ config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mer
         mermaid_tests.push_str(&note);
     }
 
-    fs::create_dir_all(out_path)?;
+    let mut mermaid_modules = frontmatter(config, config.modules_title);
+    mermaid_modules.push_str(&crate::flowchart::render_module_graph(
+        &modules,
+        &module_deps,
+    ));
+
+    let mut mermaid_calls = frontmatter(config, config.calls_title);
+    mermaid_calls.push_str(&crate::flowchart::render_call_graph(
+        &classes,
+        &file_functions_main,
+        config.external_calls,
+    ));
+
+    fs::create_dir_all(&out_path)?;
     fs::write(out_path.join("diagram.mmd"), mermaid_main)?;
     fs::write(out_path.join("diagram_tests.mmd"), mermaid_tests)?;
+    fs::write(out_path.join("diagram_modules.mmd"), mermaid_modules)?;
+    fs::write(out_path.join("diagram_calls.mmd"), mermaid_calls)?;
+    fs::write(
+        out_path.join("untested.md"),
+        untested_report(&classes, &file_functions_main, &file_functions_tests),
+    )?;
+
+    if let Some(members) = &members {
+        let mut mermaid_crates = frontmatter(config, config.crates_title);
+        mermaid_crates.push_str(&crate::flowchart::render_crate_graph(members));
+        fs::write(out_path.join("diagram_crates.mmd"), mermaid_crates)?;
+    }
+
+    Ok(())
+}
+
+/// Generate a sequence diagram of the calls made by the function `entry`,
+/// following nested calls up to `max_depth` levels, into `diagram_sequence.mmd`.
+pub fn generate_sequence_diagram(
+    config: &DiagramConfig<'_>,
+    entry: &str,
+    max_depth: usize,
+) -> Result<()> {
+    let (src_path, out_path) = resolve_dirs(config);
+    let members = match config.workspace {
+        Some(root) => Some(crate::workspace::load_workspace(root)?),
+        None => None,
+    };
+    let project = parse_sources(config, &src_path, members.as_deref())?;
+
+    let mut mermaid = frontmatter(config, entry);
+    mermaid.push_str(&crate::sequence::render_sequence(
+        &project, entry, max_depth,
+    )?);
+
+    fs::create_dir_all(&out_path)?;
+    fs::write(out_path.join("diagram_sequence.mmd"), mermaid)?;
 
     Ok(())
 }
 
+/// Parse the workspace members when there are any, `src_path` otherwise, and
+/// report module-tree warnings on stderr.
+fn parse_sources(
+    config: &DiagramConfig<'_>,
+    src_path: &Path,
+    members: Option<&[Member]>,
+) -> Result<ParsedProject> {
+    let crates: Vec<CrateSource> = match members {
+        Some(members) => members
+            .iter()
+            .map(|m| CrateSource {
+                root: m.name.clone(),
+                src: m.src_dir.clone(),
+                aliases: m.renamed.clone(),
+            })
+            .collect(),
+        // Binaries name the library by its package name.
+        None => vec![CrateSource {
+            root: CRATE_ROOT.to_string(),
+            src: src_path.to_path_buf(),
+            aliases: package_name(src_path)
+                .map(|name| (name, CRATE_ROOT.to_string()))
+                .into_iter()
+                .collect(),
+        }],
+    };
+    let project = crate::parse::parse_crates(&crates, config.test_attributes)?;
+
+    for warning in &project.warnings {
+        eprintln!("warning: {warning}");
+    }
+
+    Ok(project)
+}
+
+/// Source and output directories, falling back to manifest-based paths when
+/// the config leaves them empty (as `Default` does).
+fn resolve_dirs(config: &DiagramConfig<'_>) -> (PathBuf, PathBuf) {
+    let manifest_dir = default_manifest_dir();
+
+    let src_path = if config.src_dir.as_os_str().is_empty() {
+        manifest_dir.join("src")
+    } else {
+        config.src_dir.to_path_buf()
+    };
+
+    let out_path = if config.out_dir.as_os_str().is_empty() {
+        manifest_dir
+    } else {
+        config.out_dir.to_path_buf()
+    };
+
+    (src_path, out_path)
+}
+
+/// YAML front matter shared by every generated diagram.
+fn frontmatter(config: &DiagramConfig<'_>, title: &str) -> String {
+    format!(
+        "---\n\
+config:\n  title: {title}\n  layout: {layout}\n  theme: {theme}\n  elk:\n    mergeEdges: true\n    nodePlacementStrategy: {elk_node_placement}\n---\n",
+        title = title,
+        layout = config.layout,
+        theme = config.theme,
+        elk_node_placement = config.elk_node_placement,
+    )
+}
+
 /// Backwards-compatible helper using default config.
 pub fn generate_diagrams() -> Result<()> {
     // Build a real config with owned paths, then pass references.
@@ -245,31 +658,96 @@ pub fn generate_diagrams() -> Result<()> {
     let cfg = DiagramConfig {
         main_title: "Project",
         tests_title: "Project Tests",
+        modules_title: "Project Modules",
+        calls_title: "Project Calls",
+        crates_title: "Project Crates",
         layout: "elk",
         theme: "dark",
         elk_node_placement: "BRANDES_KOEPF",
+        trait_impls: TraitImplMode::Local,
+        public_only: false,
+        abbreviate_types: false,
+        external_calls: false,
+        signature_deps: false,
+        compact_derives: false,
+        trait_methods: false,
+        impl_locations: false,
+        dyn_implementors: false,
+        test_attributes: DEFAULT_TEST_ATTRIBUTES,
         src_dir: &src_dir_buf,
         out_dir: &out_dir_buf,
+        workspace: None,
     };
 
     generate_diagrams_with_config(&cfg)
 }
 
-fn render_functions_namespaces(out: &mut String, files: &BTreeMap<String, Vec<FunctionInfo>>) {
-    for (file_module, funcs) in files {
-        let ns_title = format!("{}.rs", file_module);
-        out.push_str(&format!("    namespace `{}` {{\n", ns_title));
-        out.push_str(&format!("        class `{}_functions` {{\n", file_module));
+/// Namespace an item is drawn in: its file, qualified with the inline `mod`
+/// blocks around it (`lib::a::b`) since Mermaid cannot nest namespaces.
+fn namespace(file_module: &str, inline_module: &str) -> String {
+    if inline_module.is_empty() {
+        file_module.to_string()
+    } else {
+        format!("{file_module}::{inline_module}")
+    }
+}
+
+/// Display title of a [`namespace`]: `lib.rs` for a whole file.
+fn namespace_title(ns: &str) -> String {
+    if ns.contains("::") {
+        ns.to_string()
+    } else {
+        format!("{ns}.rs")
+    }
+}
+
+/// Regroup per-file items by [`namespace`].
+fn by_namespace<T>(
+    files: &BTreeMap<String, Vec<T>>,
+    inline_module: impl Fn(&T) -> &str,
+) -> BTreeMap<String, Vec<&T>> {
+    let mut grouped: BTreeMap<String, Vec<&T>> = BTreeMap::new();
+    for (file_module, items) in files {
+        for item in items {
+            grouped
+                .entry(namespace(file_module, inline_module(item)))
+                .or_default()
+                .push(item);
+        }
+    }
+    grouped
+}
+
+/// One `{namespace}_{suffix}` pseudo-class per file (or inline module)
+/// listing its free functions.
+fn render_functions_namespaces(
+    out: &mut String,
+    files: &BTreeMap<String, Vec<FunctionInfo>>,
+    suffix: &str,
+    opts: RenderOptions,
+) {
+    for (ns, funcs) in by_namespace(files, |f| &f.inline_module) {
+        let funcs: Vec<&FunctionInfo> = funcs
+            .into_iter()
+            .filter(|f| !opts.public_only || f.exported)
+            .collect();
+        if funcs.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("    namespace `{}` {{\n", namespace_title(&ns)));
+        out.push_str(&format!("        class `{}_{}` {{\n", ns, suffix));
         for f in funcs {
-            let param_list = if f.params.is_empty() {
-                "".to_string()
-            } else {
-                f.params.join(", ")
-            };
+            let param_list = mermaid_params(&f.params, opts.abbreviate_types);
+            let marker = f.visibility.marker();
             let sig = if let Some(ret) = &f.ret {
-                format!("{} {}({})", ret, f.name, param_list)
+                let ret = mermaid_type(ret, opts.abbreviate_types);
+                format!("{}{} {}({})", marker, ret, f.name, param_list)
             } else {
-                format!("{}({})", f.name, param_list)
+                format!("{}{}({})", marker, f.name, param_list)
+            };
+            let sig = match &f.test {
+                Some(test) => format!("{sig} {}", test_annotation(test)),
+                None => sig,
             };
             if let Some(doc) = &f.doc {
                 out.push_str(&format!("            {} {}\n", sig, doc));
@@ -282,9 +760,341 @@ fn render_functions_namespaces(out: &mut String, files: &BTreeMap<String, Vec<Fu
     }
 }
 
+/// One `{namespace}_consts` pseudo-class per file (or inline module) listing
+/// its consts and statics as static members.
+fn render_consts_namespaces(
+    out: &mut String,
+    files: &BTreeMap<String, Vec<Constant>>,
+    opts: RenderOptions,
+) {
+    for (ns, consts) in by_namespace(files, |c| &c.inline_module) {
+        let consts: Vec<&Constant> = consts
+            .into_iter()
+            .filter(|c| !opts.public_only || c.exported)
+            .collect();
+        if consts.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("    namespace `{}` {{\n", namespace_title(&ns)));
+        out.push_str(&format!("        class `{}_consts` {{\n", ns));
+        for c in consts {
+            let keyword = match (c.is_static, c.mutable) {
+                (true, true) => "static mut ",
+                (true, false) => "static ",
+                _ => "",
+            };
+            out.push_str(&format!(
+                "            {}{}{} {}$\n",
+                c.visibility.marker(),
+                keyword,
+                mermaid_type(&c.ty, opts.abbreviate_types),
+                c.name
+            ));
+        }
+        out.push_str("        }\n");
+        out.push_str("    }\n");
+    }
+}
+
+/// `..>` edges from `from` to every local type in the signatures of `funcs`,
+/// labelled with the functions using it; targets for which `skip` holds are
+/// left out.
+fn dependency_edges<'a>(
+    from: &str,
+    funcs: impl Iterator<Item = &'a FunctionInfo>,
+    skip: impl Fn(&str) -> bool,
+) -> Vec<String> {
+    let mut users: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for f in funcs {
+        for target in f.uses.iter().filter(|t| !skip(t)) {
+            users.entry(target).or_default().insert(&f.name);
+        }
+    }
+
+    users
+        .into_iter()
+        .map(|(target, names)| {
+            let names: Vec<String> = names.into_iter().map(|n| format!("{n}()")).collect();
+            format!(
+                "    {} ..> {} : {}\n",
+                from,
+                mermaid_id(target),
+                names.join(", ")
+            )
+        })
+        .collect()
+}
+
+/// A non-test function or type that tests can exercise, as drawn in the
+/// tests diagram.
+struct TestTarget {
+    /// Node the edge points at: the owning class, or the file's
+    /// `_functions` pseudo-class for free functions.
+    node: String,
+    /// Declaration of `node`, which is otherwise only in the main diagram.
+    stub: String,
+    /// Edge label entry (`render()`, `struct literal`).
+    member: String,
+}
+
+/// Every non-test free function, method and type by full path.
+fn test_targets<'a>(
+    classes: &'a HashMap<String, Class>,
+    functions: &'a BTreeMap<String, Vec<FunctionInfo>>,
+) -> HashMap<&'a str, TestTarget> {
+    let mut targets = HashMap::new();
+    for (ns, funcs) in by_namespace(functions, |f| &f.inline_module) {
+        let node = format!("`{}_functions`", ns);
+        for f in funcs {
+            targets.insert(
+                f.path.as_str(),
+                TestTarget {
+                    stub: format!("class {}", node),
+                    node: node.clone(),
+                    member: format!("{}()", f.name),
+                },
+            );
+        }
+    }
+    for class in classes.values().filter(|c| !is_test_class(c)) {
+        let node = mermaid_id(&class.path);
+        let stub = format!("class {}[\"{}\"]", node, class.name);
+        for m in &class.methods {
+            targets.insert(
+                m.path.as_str(),
+                TestTarget {
+                    node: node.clone(),
+                    stub: stub.clone(),
+                    member: format!("{}()", m.name),
+                },
+            );
+        }
+        targets.insert(
+            class.path.as_str(),
+            TestTarget {
+                node,
+                stub,
+                member: "constructed".into(),
+            },
+        );
+    }
+    targets
+}
+
+/// Paths of the functions a test calls and the types it constructs.
+fn exercised(test: &FunctionInfo) -> impl Iterator<Item = &str> {
+    test.calls()
+        .into_iter()
+        .filter_map(|call| call.target.as_deref())
+        .chain(test.constructs.iter().map(String::as_str))
+}
+
+/// Dependency edges from each file's `_tests` pseudo-class to the functions
+/// and types its tests exercise, labelled with what is used.
+fn render_test_links(
+    out: &mut String,
+    classes: &HashMap<String, Class>,
+    functions: &BTreeMap<String, Vec<FunctionInfo>>,
+    tests: &BTreeMap<String, Vec<FunctionInfo>>,
+) {
+    let targets = test_targets(classes, functions);
+
+    let mut stubs = BTreeSet::new();
+    let mut links: BTreeMap<(String, &str), BTreeSet<&str>> = BTreeMap::new();
+    for (ns, funcs) in by_namespace(tests, |f| &f.inline_module) {
+        for f in funcs {
+            for path in exercised(f) {
+                let Some(target) = targets.get(path) else {
+                    continue;
+                };
+                stubs.insert(&target.stub);
+                links
+                    .entry((format!("`{}_tests`", ns), &target.node))
+                    .or_default()
+                    .insert(&target.member);
+            }
+        }
+    }
+
+    for stub in stubs {
+        out.push_str(&format!("    {}\n", stub));
+    }
+    for ((from, to), members) in links {
+        out.push_str(&format!(
+            "    {} ..> {} : {}\n",
+            from,
+            to,
+            members.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+}
+
+/// Markdown list of public functions and methods that no test calls.
+fn untested_report(
+    classes: &HashMap<String, Class>,
+    functions: &BTreeMap<String, Vec<FunctionInfo>>,
+    tests: &BTreeMap<String, Vec<FunctionInfo>>,
+) -> String {
+    let tested: BTreeSet<&str> = tests.values().flatten().flat_map(exercised).collect();
+
+    let public = functions.values().flatten().chain(
+        classes
+            .values()
+            .filter(|c| !is_test_class(c))
+            .flat_map(|c| &c.methods),
+    );
+    let mut untested: Vec<&str> = public
+        .filter(|f| f.exported && !tested.contains(f.path.as_str()))
+        // Trait methods without a body have nothing to test.
+        .filter(|f| !f.is_abstract)
+        .map(|f| f.path.as_str())
+        .collect();
+    untested.sort();
+
+    let mut out = String::from(
+        "# Untested public functions\n\nPublic functions and methods that no test calls directly.\n\n",
+    );
+    if untested.is_empty() {
+        out.push_str("None.\n");
+    }
+    for path in untested {
+        out.push_str(&format!("- `{}`\n", path));
+    }
+    out
+}
+
+/// Bracketed test details shown after a test's signature
+/// (`[tokio::test, ignore, 3 cases]`).
+fn test_annotation(test: &TestInfo) -> String {
+    let mut parts = Vec::new();
+    if test.marker != "test" {
+        parts.push(test.marker.clone());
+    }
+    if test.ignored {
+        parts.push("ignore".into());
+    }
+    if test.should_panic {
+        parts.push("should_panic".into());
+    }
+    match test.cases {
+        0 => {}
+        1 => parts.push("1 case".into()),
+        n => parts.push(format!("{n} cases")),
+    }
+    if parts.is_empty() {
+        parts.push("test".into());
+    }
+    format!("[{}]", parts.join(", "))
+}
+
+/// Mermaid node id for a class path (`crate::net::Config` -> `crate__net__Config`).
+fn mermaid_id(path: &str) -> String {
+    path.replace("::", "__")
+}
+
+/// A parameter list (`x: Vec<T>`, ...) with every type in Mermaid syntax.
+fn mermaid_params(params: &[String], abbreviate: bool) -> String {
+    params
+        .iter()
+        .map(|p| mermaid_type(p, abbreviate))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Convert a Rust type to Mermaid's generic syntax (`Vec<T>` -> `Vec~T~`),
+/// optionally dropping path prefixes (`std::rc::Rc<T>` -> `Rc~T~`).
+fn mermaid_type(ty: &str, abbreviate: bool) -> String {
+    let mut out = String::with_capacity(ty.len());
+    let mut chars = ty.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Keep `->` in `fn(A) -> B` / `Fn(A) -> B` intact.
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                out.push_str("->");
+            }
+            '<' | '>' => out.push('~'),
+            ':' if abbreviate && chars.peek() == Some(&':') => {
+                chars.next();
+                while out.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    out.pop();
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Ghost nodes for traits that are implemented but not defined locally.
+fn render_external_traits(out: &mut String, traits: &BTreeSet<&String>) {
+    for name in traits {
+        out.push_str(&format!("    class `{}` {{\n", name));
+        out.push_str("        <<external>>\n");
+        out.push_str("    }\n");
+    }
+}
+
+/// Test-only types: defined in a test file or a `#[cfg(test)]` module.
+fn is_test_class(class: &Class) -> bool {
+    class.is_test || is_test_file(&class.file)
+}
+
 fn is_test_file(file_module: &str) -> bool {
     file_module.starts_with("tests")
         || file_module.contains("/tests/")
         || file_module.ends_with("_test")
         || file_module.ends_with("_tests")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_generics_to_mermaid_syntax() {
+        assert_eq!(mermaid_type("u8", false), "u8");
+        assert_eq!(mermaid_type("Vec<T>", false), "Vec~T~");
+        assert_eq!(
+            mermaid_type(
+                "std::collections::HashMap<String, Vec<FunctionInfo>>",
+                false
+            ),
+            "std::collections::HashMap~String, Vec~FunctionInfo~~"
+        );
+        assert_eq!(mermaid_type("&'a [u8]", false), "&'a [u8]");
+    }
+
+    #[test]
+    fn keeps_arrows_of_function_types() {
+        assert_eq!(mermaid_type("fn(u8) -> bool", false), "fn(u8) -> bool");
+        assert_eq!(
+            mermaid_type("Box<dyn Fn(Vec<u8>) -> Option<u8>>", false),
+            "Box~dyn Fn(Vec~u8~) -> Option~u8~~"
+        );
+    }
+
+    #[test]
+    fn strips_path_prefixes_when_abbreviating() {
+        assert_eq!(
+            mermaid_type("std::collections::HashMap<String, Vec<FunctionInfo>>", true),
+            "HashMap~String, Vec~FunctionInfo~~"
+        );
+        assert_eq!(
+            mermaid_type("Option<std::rc::Rc<crate::parse::Class>>", true),
+            "Option~Rc~Class~~"
+        );
+        assert_eq!(
+            mermaid_params(
+                &[
+                    "&self".into(),
+                    "map: &std::collections::BTreeMap<K, V>".into()
+                ],
+                true
+            ),
+            "&self, map: &BTreeMap~K, V~"
+        );
+    }
+}
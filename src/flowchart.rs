@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::parse::{Callee, Class, FunctionInfo};
+use crate::workspace::Member;
+
+/// Stroke used for edges and nodes that take part in a dependency cycle.
+const CYCLE_STYLE: &str = "stroke:#e74c3c,stroke-width:3px";
+
+/// Render the module dependency graph as a Mermaid `flowchart` body.
+///
+/// Edge labels count the distinct items referenced across the edge; edges
+/// and modules that sit on a cycle are highlighted.
+pub fn render_module_graph(
+    modules: &BTreeSet<String>,
+    deps: &BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+) -> String {
+    let mut out = String::from("flowchart LR\n");
+
+    for module in modules {
+        out.push_str(&format!("    {}[\"{}\"]\n", node_id(module), module));
+    }
+
+    // Modules never depend on themselves, so an edge inside one strongly
+    // connected component is always part of a cycle.
+    let components = strongly_connected(modules, deps);
+    let in_cycle = |from: &String, to: &String| components.get(from) == components.get(to);
+
+    let mut cycle_edges = Vec::new();
+    let mut cycle_nodes = BTreeSet::new();
+    let mut index = 0;
+    for (from, targets) in deps {
+        for (to, items) in targets {
+            out.push_str(&format!(
+                "    {} -->|{}| {}\n",
+                node_id(from),
+                items.len(),
+                node_id(to)
+            ));
+            if in_cycle(from, to) {
+                cycle_edges.push(index.to_string());
+                cycle_nodes.insert(node_id(from));
+                cycle_nodes.insert(node_id(to));
+            }
+            index += 1;
+        }
+    }
+
+    if !cycle_edges.is_empty() {
+        out.push_str(&format!(
+            "    linkStyle {} {}\n",
+            cycle_edges.join(","),
+            CYCLE_STYLE
+        ));
+        out.push_str(&format!("    classDef cycle {}\n", CYCLE_STYLE));
+        out.push_str(&format!(
+            "    class {} cycle\n",
+            cycle_nodes.into_iter().collect::<Vec<_>>().join(",")
+        ));
+    }
+
+    out
+}
+
+/// Render the workspace crates and their `[dependencies]` on each other as a
+/// Mermaid `flowchart` body.
+pub fn render_crate_graph(members: &[Member]) -> String {
+    let mut out = String::from("flowchart LR\n");
+
+    for member in members {
+        out.push_str(&format!(
+            "    {}[\"{}\"]\n",
+            sanitize(&member.name),
+            member.name
+        ));
+    }
+    for member in members {
+        for dep in &member.dependencies {
+            out.push_str(&format!(
+                "    {} --> {}\n",
+                sanitize(&member.name),
+                sanitize(dep)
+            ));
+        }
+    }
+
+    out
+}
+
+/// Render the static call graph as a Mermaid `flowchart` body, with one
+/// subgraph per file module.
+///
+/// Calls that do not resolve to a project function are drawn as dashed
+/// external nodes when `external` is set, and dropped otherwise.
+pub fn render_call_graph(
+    classes: &HashMap<String, Class>,
+    file_functions: &BTreeMap<String, Vec<FunctionInfo>>,
+    external: bool,
+) -> String {
+    // Every function with the file it lives in and its display label.
+    let mut functions: Vec<(&str, &FunctionInfo, String)> = Vec::new();
+    for (file_module, funcs) in file_functions {
+        for f in funcs {
+            functions.push((file_module, f, format!("{}()", f.name)));
+        }
+    }
+    for class in classes.values() {
+        for m in &class.methods {
+            functions.push((&class.file, m, format!("{}::{}()", class.name, m.name)));
+        }
+    }
+
+    let mut edges = BTreeSet::new();
+    let mut external_nodes = BTreeMap::new();
+    for (_, f, _) in &functions {
+        for call in f.calls() {
+            let target = match &call.target {
+                Some(target) => node_id(target),
+                None if external => {
+                    let label = match &call.callee {
+                        Callee::Path(path) => format!("{path}()"),
+                        Callee::SelfMethod(m) => format!("self.{m}()"),
+                        Callee::Method(m) => format!(".{m}()"),
+                    };
+                    let id = format!("ext_{}", sanitize(&label));
+                    external_nodes.insert(id.clone(), label);
+                    id
+                }
+                None => continue,
+            };
+            edges.insert((node_id(&f.path), target));
+        }
+    }
+
+    let connected: BTreeSet<&String> = edges.iter().flat_map(|(a, b)| [a, b]).collect();
+
+    let mut by_file: BTreeMap<&str, BTreeSet<(String, &String)>> = BTreeMap::new();
+    for (file, f, label) in &functions {
+        let id = node_id(&f.path);
+        if connected.contains(&id) {
+            by_file.entry(file).or_default().insert((id, label));
+        }
+    }
+
+    let mut out = String::from("flowchart LR\n");
+
+    for (file, nodes) in &by_file {
+        out.push_str(&format!(
+            "    subgraph {}[\"{}.rs\"]\n",
+            sanitize(file),
+            file
+        ));
+        for (id, label) in nodes {
+            out.push_str(&format!("        {}[\"{}\"]\n", id, label));
+        }
+        out.push_str("    end\n");
+    }
+
+    for (id, label) in &external_nodes {
+        out.push_str(&format!("    {}[\"{}\"]:::external\n", id, label));
+    }
+    if !external_nodes.is_empty() {
+        out.push_str("    classDef external stroke-dasharray: 5 5\n");
+    }
+
+    for (from, to) in &edges {
+        out.push_str(&format!("    {} --> {}\n", from, to));
+    }
+
+    out
+}
+
+/// Replace everything Mermaid does not accept in a node id with `_`.
+fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Flowchart node id for an item path (`crate::net` -> `crate__net`).
+fn node_id(path: &str) -> String {
+    path.replace("::", "__")
+}
+
+/// Tarjan's algorithm: map every node to the index of its strongly connected
+/// component.
+fn strongly_connected<'a>(
+    nodes: &'a BTreeSet<String>,
+    edges: &'a BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+) -> BTreeMap<&'a String, usize> {
+    struct State<'a> {
+        edges: &'a BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+        next_index: usize,
+        index: BTreeMap<&'a String, usize>,
+        low: BTreeMap<&'a String, usize>,
+        stack: Vec<&'a String>,
+        on_stack: BTreeSet<&'a String>,
+        component: BTreeMap<&'a String, usize>,
+        components: usize,
+    }
+
+    fn visit<'a>(state: &mut State<'a>, node: &'a String) {
+        state.index.insert(node, state.next_index);
+        state.low.insert(node, state.next_index);
+        state.next_index += 1;
+        state.stack.push(node);
+        state.on_stack.insert(node);
+
+        for next in state.edges.get(node).into_iter().flat_map(|t| t.keys()) {
+            if !state.index.contains_key(next) {
+                visit(state, next);
+                let low = state.low[node].min(state.low[next]);
+                state.low.insert(node, low);
+            } else if state.on_stack.contains(next) {
+                let low = state.low[node].min(state.index[next]);
+                state.low.insert(node, low);
+            }
+        }
+
+        if state.low[node] == state.index[node] {
+            while let Some(member) = state.stack.pop() {
+                state.on_stack.remove(member);
+                state.component.insert(member, state.components);
+                if member == node {
+                    break;
+                }
+            }
+            state.components += 1;
+        }
+    }
+
+    let mut state = State {
+        edges,
+        next_index: 0,
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        component: BTreeMap::new(),
+        components: 0,
+    };
+
+    for node in nodes.iter().chain(edges.keys()) {
+        if !state.index.contains_key(node) {
+            visit(&mut state, node);
+        }
+    }
+
+    state.component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{ParsedProject, parse_project};
+
+    #[test]
+    fn groups_cycles_into_components() {
+        // a <-> b -> c -> d -> e -> c, f alone
+        let nodes: BTreeSet<String> = ["a", "b", "c", "d", "e", "f"].map(String::from).into();
+        let mut edges: BTreeMap<String, BTreeMap<String, BTreeSet<String>>> = BTreeMap::new();
+        for (from, to) in [
+            ("a", "b"),
+            ("b", "a"),
+            ("b", "c"),
+            ("c", "d"),
+            ("d", "e"),
+            ("e", "c"),
+        ] {
+            edges
+                .entry(from.to_string())
+                .or_default()
+                .insert(to.to_string(), BTreeSet::new());
+        }
+
+        let components = strongly_connected(&nodes, &edges);
+        let of = |node: &str| components[&node.to_string()];
+        assert_eq!(components.len(), 6);
+        assert_eq!(of("a"), of("b"));
+        assert_eq!(of("c"), of("d"));
+        assert_eq!(of("c"), of("e"));
+        assert_ne!(of("a"), of("c"));
+        assert_ne!(of("f"), of("a"));
+        assert_ne!(of("f"), of("c"));
+    }
+
+    fn project(test: &str, lib: &str) -> ParsedProject {
+        let dir = std::env::temp_dir().join(format!("rust-to-mermaid-flowchart-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.rs"), lib).unwrap();
+        parse_project(&dir).unwrap()
+    }
+
+    const LIB: &str = "pub struct Shape;
+        impl Shape {
+            pub fn new() -> Self { Shape }
+            pub fn area(&self) -> u8 { self.side() }
+            fn side(&self) -> u8 { 0 }
+        }
+        pub fn build() -> u8 { Shape::new().area(); other::thing(); Shape::new().area() }";
+
+    #[test]
+    fn draws_resolved_calls_by_file() {
+        let project = project("resolved", LIB);
+        let graph = render_call_graph(&project.classes, &project.file_functions_main, false);
+        assert!(graph.contains("    subgraph lib[\"lib.rs\"]\n"));
+        assert!(graph.contains("        crate__Shape__area[\"Shape::area()\"]\n"));
+        assert!(graph.contains("    crate__build --> crate__Shape__new\n"));
+        assert!(graph.contains("    crate__Shape__area --> crate__Shape__side\n"));
+        assert_eq!(
+            graph.matches("crate__build --> crate__Shape__new").count(),
+            1
+        );
+        assert!(!graph.contains("ext_"));
+    }
+
+    #[test]
+    fn draws_unresolved_calls_only_when_asked() {
+        let project = project("external", LIB);
+        let graph = render_call_graph(&project.classes, &project.file_functions_main, true);
+        assert!(graph.contains("    ext_other__thing__[\"other::thing()\"]:::external\n"));
+        assert!(graph.contains("    ext__area__[\".area()\"]:::external\n"));
+        assert!(graph.contains("    crate__build --> ext_other__thing__\n"));
+        assert!(graph.contains("classDef external stroke-dasharray: 5 5"));
+    }
+}
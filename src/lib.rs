@@ -1,2 +1,8 @@
+mod attributes;
 pub mod build_diagram;
+mod flowchart;
+mod module_tree;
 pub mod parse;
+mod resolve;
+mod sequence;
+pub mod workspace;
@@ -1,10 +1,8 @@
-mod build_diagram;
-mod parse;
-
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
-use crate::build_diagram::DiagramConfig;
+use rust_to_mermaid::build_diagram::{self, DiagramConfig, TraitImplMode};
+use rust_to_mermaid::parse::DEFAULT_TEST_ATTRIBUTES;
 
 /// CLI configuration for rust-to-mermaid.
 #[derive(Debug, Parser)]
@@ -29,6 +27,23 @@ struct Cli {
     #[arg(long, default_value = "Project Tests")]
     tests_title: String,
 
+    /// Module dependency diagram title
+    #[arg(long, default_value = "Project Modules")]
+    modules_title: String,
+
+    /// Call graph diagram title
+    #[arg(long, default_value = "Project Calls")]
+    calls_title: String,
+
+    /// Crate dependency diagram title (workspaces only)
+    #[arg(long, default_value = "Project Crates")]
+    crates_title: String,
+
+    /// Workspace root or its Cargo.toml; scans every `[workspace] members`
+    /// crate instead of `--src`
+    #[arg(long, value_name = "WORKSPACE")]
+    workspace: Option<PathBuf>,
+
     /// Layout engine (e.g. elk, dagre)
     #[arg(long, default_value = "elk")]
     layout: String,
@@ -40,6 +55,65 @@ struct Cli {
     /// ELK node placement strategy
     #[arg(long, default_value = "BRANDES_KOEPF")]
     elk_node_placement: String,
+
+    /// Which trait implementations to draw (local traits only, or all)
+    #[arg(long, value_enum, default_value_t = TraitImplMode::Local)]
+    trait_impls: TraitImplMode,
+
+    /// Only include the crate's public (`pub`) API in the main diagram
+    #[arg(long)]
+    public_only: bool,
+
+    /// Abbreviate type paths in class boxes (`std::rc::Rc<T>` -> `Rc~T~`)
+    #[arg(long)]
+    short_types: bool,
+
+    /// Show calls to functions outside the project in the call graph
+    #[arg(long)]
+    external_calls: bool,
+
+    /// Draw dependency edges to local types used in method and function
+    /// signatures
+    #[arg(long)]
+    signature_deps: bool,
+
+    /// Show `#[derive(...)]` traits as a `<<derive: ...>>` line instead of
+    /// edges
+    #[arg(long)]
+    compact_derives: bool,
+
+    /// List the methods of trait impls in the class boxes
+    #[arg(long)]
+    trait_methods: bool,
+
+    /// Note the modules holding `impl` blocks for types declared elsewhere
+    #[arg(long)]
+    impl_locations: bool,
+
+    /// Draw dashed edges from `dyn Trait` fields to every local implementor
+    #[arg(long)]
+    dyn_implementors: bool,
+
+    /// Extra attribute path marking a test function (repeatable; `name!` for
+    /// test macros like `proptest!`), on top of the common test frameworks
+    #[arg(long = "test-attribute", value_name = "PATH")]
+    test_attributes: Vec<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write a sequence diagram of the calls made by one function
+    Sequence {
+        /// Function path, e.g. `main` or `build_diagram::generate_diagrams`
+        function: String,
+
+        /// How many levels of nested calls to follow
+        #[arg(long, default_value_t = 3)]
+        depth: usize,
+    },
 }
 
 fn main() {
@@ -48,17 +122,44 @@ fn main() {
     let src = cli.src;
     let out = cli.out;
 
+    let test_attributes: Vec<&str> = DEFAULT_TEST_ATTRIBUTES
+        .iter()
+        .copied()
+        .chain(cli.test_attributes.iter().map(String::as_str))
+        .collect();
+
     let config = DiagramConfig {
         main_title: &cli.main_title,
         tests_title: &cli.tests_title,
+        modules_title: &cli.modules_title,
+        calls_title: &cli.calls_title,
+        crates_title: &cli.crates_title,
         layout: &cli.layout,
         theme: &cli.theme,
         elk_node_placement: &cli.elk_node_placement,
+        trait_impls: cli.trait_impls,
+        public_only: cli.public_only,
+        abbreviate_types: cli.short_types,
+        external_calls: cli.external_calls,
+        signature_deps: cli.signature_deps,
+        compact_derives: cli.compact_derives,
+        trait_methods: cli.trait_methods,
+        impl_locations: cli.impl_locations,
+        dyn_implementors: cli.dyn_implementors,
+        test_attributes: &test_attributes,
         src_dir: &src,
         out_dir: &out,
+        workspace: cli.workspace.as_deref(),
+    };
+
+    let result = match &cli.command {
+        Some(Command::Sequence { function, depth }) => {
+            build_diagram::generate_sequence_diagram(&config, function, *depth)
+        }
+        None => build_diagram::generate_diagrams_with_config(&config),
     };
 
-    if let Err(e) = build_diagram::generate_diagrams_with_config(&config) {
+    if let Err(e) = result {
         eprintln!("Error generating diagrams: {e}");
         std::process::exit(1);
     }
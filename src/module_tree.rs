@@ -0,0 +1,333 @@
+use anyhow::Result;
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+use tree_sitter::{Node, Parser};
+use walkdir::WalkDir;
+
+use crate::attributes::{inner_attributes, is_cfg_test, outer_attributes};
+
+/// A source file reached through the module tree.
+pub struct SourceFile {
+    pub path: PathBuf,
+    /// Module path of the file (`crate::net` for `net/mod.rs`).
+    pub module: String,
+    /// Root module of the crate the file is compiled into: `root` for the
+    /// library, its own module for every binary.
+    pub crate_root: String,
+    pub content: String,
+    /// Only compiled for tests (`#[cfg(test)] mod tests;`, `#![cfg(test)]`).
+    pub is_test: bool,
+}
+
+/// A file still to be read, with the directory its `mod x;` declarations
+/// resolve against.
+struct Pending {
+    path: PathBuf,
+    module: String,
+    crate_root: String,
+    dir: PathBuf,
+    is_test: bool,
+}
+
+/// Collect the files of the crate whose sources live in `src_dir` by
+/// following `mod` declarations from `lib.rs`, `main.rs` and `bin/*.rs`,
+/// with `root` as the crate's top-level module. Binaries next to a library
+/// are crates of their own, rooted at `root::main` and `root::bin::name`.
+///
+/// Missing module files and `.rs` files under `src_dir` that no `mod`
+/// reaches are reported in `warnings`. A plain file as `src_dir` is taken
+/// as the crate root itself.
+pub fn crate_files(
+    parser: &mut Parser,
+    root: &str,
+    src_dir: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<SourceFile>> {
+    let mut queue = VecDeque::new();
+    let root_file = |path: PathBuf, module: String| Pending {
+        dir: path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        path,
+        crate_root: module.clone(),
+        module,
+        is_test: false,
+    };
+
+    if src_dir.is_file() {
+        queue.push_back(root_file(src_dir.to_path_buf(), root.to_string()));
+    } else {
+        let lib = src_dir.join("lib.rs");
+        let main = src_dir.join("main.rs");
+        if lib.is_file() {
+            queue.push_back(root_file(lib.clone(), root.to_string()));
+        }
+        if main.is_file() {
+            let module = if lib.is_file() {
+                format!("{root}::main")
+            } else {
+                root.to_string()
+            };
+            queue.push_back(root_file(main, module));
+        }
+        // Every binary under `bin/` is its own crate root.
+        if let Ok(entries) = fs::read_dir(src_dir.join("bin")) {
+            let mut bins: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter_map(|p| match p.extension() {
+                    Some(ext) if ext == "rs" => Some(p),
+                    _ => Some(p.join("main.rs")).filter(|m| m.is_file()),
+                })
+                .collect();
+            bins.sort();
+            for path in bins {
+                let name = match path.file_stem().and_then(|s| s.to_str()) {
+                    Some("main") => path.parent().and_then(|p| p.file_name()),
+                    _ => path.file_stem(),
+                };
+                let name = name.map(|n| n.to_string_lossy().to_string());
+                if let Some(name) = name {
+                    queue.push_back(root_file(path, format!("{root}::bin::{name}")));
+                }
+            }
+        }
+    }
+
+    if queue.is_empty() {
+        warnings.push(format!(
+            "{}: no lib.rs or main.rs to start the module tree from",
+            src_dir.display()
+        ));
+    }
+
+    let mut files = Vec::new();
+    let mut seen = BTreeSet::new();
+    while let Some(pending) = queue.pop_front() {
+        let key = pending
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| pending.path.clone());
+        if !seen.insert(key) {
+            continue;
+        }
+
+        let content = fs::read_to_string(&pending.path)?;
+        let tree = parser.parse(&content, None).unwrap();
+        let is_test = pending.is_test || is_cfg_test(&inner_attributes(tree.root_node(), &content));
+        declared_modules(
+            tree.root_node(),
+            &content,
+            &pending,
+            None,
+            is_test,
+            &mut queue,
+            warnings,
+        );
+
+        files.push(SourceFile {
+            path: pending.path,
+            module: pending.module,
+            crate_root: pending.crate_root,
+            content,
+            is_test,
+        });
+    }
+
+    if src_dir.is_dir() {
+        let mut orphans: Vec<PathBuf> = WalkDir::new(src_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+            .filter(|p| !seen.contains(&p.canonicalize().unwrap_or_else(|_| p.clone())))
+            .collect();
+        orphans.sort();
+        for orphan in orphans {
+            warnings.push(format!(
+                "{} is not part of the module tree of `{root}`",
+                orphan.display()
+            ));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Queue the files behind every `mod x;` in `node`, descending into inline
+/// `mod x { }` bodies. `inline` is the directory of the innermost enclosing
+/// inline module, which `#[path]` is relative to inside one; `is_test` is
+/// set under `#[cfg(test)]`.
+fn declared_modules(
+    node: Node,
+    src: &str,
+    file: &Pending,
+    inline: Option<(&str, &Path)>,
+    is_test: bool,
+    queue: &mut VecDeque<Pending>,
+    warnings: &mut Vec<String>,
+) {
+    let (module, dir) = inline.unwrap_or((&file.module, &file.dir));
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind() != "mod_item" {
+            continue;
+        }
+        let Some(name) = child
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(src.as_bytes()).ok())
+        else {
+            continue;
+        };
+        let child_module = format!("{module}::{name}");
+        let child_dir = dir.join(name);
+        let attrs = outer_attributes(child, src);
+        let is_test = is_test || is_cfg_test(&attrs);
+
+        if let Some(body) = child.child_by_field_name("body") {
+            declared_modules(
+                body,
+                src,
+                file,
+                Some((&child_module, &child_dir)),
+                is_test,
+                queue,
+                warnings,
+            );
+            continue;
+        }
+
+        // `#[path]` files own their directory, like `mod.rs`.
+        let path_attr = attrs
+            .iter()
+            .rev()
+            .find(|a| a.path == "path")
+            .and_then(|a| a.value.as_deref());
+        let found = match path_attr {
+            Some(path) => {
+                let file_dir = file.path.parent().unwrap_or(Path::new("."));
+                let base = if inline.is_some() { dir } else { file_dir };
+                let path = base.join(path);
+                path.is_file().then(|| {
+                    let dir = path.parent().unwrap_or(base).to_path_buf();
+                    (path, dir)
+                })
+            }
+            None => [dir.join(format!("{name}.rs")), child_dir.join("mod.rs")]
+                .into_iter()
+                .find(|p| p.is_file())
+                .map(|p| (p, child_dir.clone())),
+        };
+
+        match found {
+            Some((path, dir)) => queue.push_back(Pending {
+                path,
+                module: child_module,
+                crate_root: file.crate_root.clone(),
+                dir,
+                is_test,
+            }),
+            None => warnings.push(format!(
+                "{}: no file for `mod {name};`",
+                file.path.display()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `files` (path relative to the source directory -> content) into a
+    /// fresh directory named after the test.
+    fn src_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-to-mermaid-{test}"));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn walk(dir: &Path) -> (Vec<SourceFile>, Vec<String>) {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_rust::language()).unwrap();
+        let mut warnings = Vec::new();
+        let files = crate_files(&mut parser, "crate", dir, &mut warnings).unwrap();
+        (files, warnings)
+    }
+
+    fn modules(files: &[SourceFile]) -> Vec<(&str, bool)> {
+        files
+            .iter()
+            .map(|f| (f.module.as_str(), f.is_test))
+            .collect()
+    }
+
+    #[test]
+    fn follows_path_attributes() {
+        let dir = src_dir(
+            "path-attributes",
+            &[
+                (
+                    "lib.rs",
+                    "#[path = \"impls/net.rs\"] mod net;\nmod outer { mod inner; }",
+                ),
+                ("impls/net.rs", ""),
+                ("outer/inner.rs", "#![cfg(test)]"),
+            ],
+        );
+        let (files, warnings) = walk(&dir);
+        assert_eq!(
+            modules(&files),
+            [
+                ("crate", false),
+                ("crate::net", false),
+                ("crate::outer::inner", true),
+            ]
+        );
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn reports_missing_and_orphan_files() {
+        let dir = src_dir("orphans", &[("lib.rs", "mod gone;"), ("stray.rs", "")]);
+        let (files, warnings) = walk(&dir);
+        assert_eq!(modules(&files), [("crate", false)]);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[0].ends_with("no file for `mod gone;`"));
+        assert!(warnings[1].ends_with("stray.rs is not part of the module tree of `crate`"));
+    }
+
+    #[test]
+    fn roots_binaries_apart_from_the_library() {
+        let dir = src_dir(
+            "binaries",
+            &[
+                ("lib.rs", ""),
+                ("main.rs", "mod cli;"),
+                ("cli.rs", ""),
+                ("bin/tool.rs", ""),
+            ],
+        );
+        let (files, _) = walk(&dir);
+        let roots: Vec<(&str, &str)> = files
+            .iter()
+            .map(|f| (f.module.as_str(), f.crate_root.as_str()))
+            .collect();
+        assert_eq!(
+            roots,
+            [
+                ("crate", "crate"),
+                ("crate::main", "crate::main"),
+                ("crate::bin::tool", "crate::bin::tool"),
+                ("crate::main::cli", "crate::main"),
+            ]
+        );
+    }
+}
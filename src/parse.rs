@@ -1,33 +1,227 @@
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
-    fs,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    mem,
     path::{Path, PathBuf},
 };
 
 use tree_sitter::{Node, Parser};
-use walkdir::WalkDir;
+
+use crate::attributes::{Attribute, derives, is_cfg_test, outer_attributes, repr};
+use crate::module_tree::crate_files;
+use crate::resolve::{CRATE_ROOT, ItemKind, Resolver, short_name, use_paths};
+
+/// Declared visibility of an item, field or method.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Visibility {
+    /// `pub`
+    Public,
+    /// `pub(crate)`
+    Crate,
+    /// `pub(super)` / `pub(in path)`
+    Restricted,
+    /// No modifier (or `pub(self)`).
+    #[default]
+    Private,
+}
 
 #[derive(Default)]
 pub struct Class {
+    /// Short display name ("Config").
     pub name: String,
+    /// Full module path, unique across the project ("crate::net::Config").
+    pub path: String,
     pub file: String,
+    /// Inline `mod` blocks between the file and this item (`a::b`); empty
+    /// at file level.
+    pub inline_module: String,
+    pub visibility: Visibility,
+    /// `pub` and reachable from outside the crate: every enclosing module is
+    /// `pub`, or a `pub use` re-exports it.
+    pub exported: bool,
     pub stereotype: Option<String>,
     /// First doc-comment line for this type, if present.
     pub doc: Option<String>,
-    pub fields: Vec<String>,
-    pub methods: Vec<String>,
+    pub fields: Vec<Field>,
+    pub methods: Vec<FunctionInfo>,
     pub relationships: BTreeSet<Relationship>,
+    /// Defined inside test-only code (a `#[cfg(test)]` module).
+    pub is_test: bool,
+    /// Type and const parameter names (`K`, `V`, `N`); lifetimes are left out.
+    pub generics: Vec<String>,
+    /// Arguments of `#[repr(...)]` (`u8`, `C, align(8)`).
+    pub repr: Option<String>,
+    /// Marked `#[non_exhaustive]`.
+    pub non_exhaustive: bool,
     pub trait_impls: BTreeSet<String>,
+    /// Traits from `#[derive(...)]`, in source order; also in `trait_impls`.
+    pub derives: Vec<String>,
+    /// Modules containing an `impl` block for this type.
+    pub impl_modules: BTreeSet<String>,
+    /// Supertrait bounds of a trait (`trait A: B + C`).
+    pub supertraits: BTreeSet<String>,
+}
+
+pub struct Field {
+    pub name: String,
+    /// Declared type as written in the source ("Vec<String>", ...)
+    pub ty: Option<String>,
+    pub visibility: Visibility,
+    /// Explicit discriminant of an enum variant (`Empty = 3`).
+    pub discriminant: Option<String>,
+}
+
+/// A module-level `const` or `static` item.
+pub struct Constant {
+    pub name: String,
+    /// Declared type as written in the source.
+    pub ty: String,
+    pub visibility: Visibility,
+    /// `static` rather than `const`.
+    pub is_static: bool,
+    /// `static mut`.
+    pub mutable: bool,
+    /// See [`Class::exported`].
+    pub exported: bool,
+    /// Inline `mod` blocks between the file and this item (`a::b`); empty
+    /// at file level.
+    pub inline_module: String,
 }
 
 pub struct FunctionInfo {
     pub name: String,
+    /// Full path ("crate::parse::parse_project", "crate::parse::Class::render").
+    pub path: String,
+    /// Module the function is defined in; call paths are resolved from here.
+    pub module: String,
+    /// Inline `mod` blocks between the file and this item (`a::b`); empty
+    /// at file level.
+    pub inline_module: String,
+    pub visibility: Visibility,
+    /// See [`Class::exported`].
+    pub exported: bool,
     /// First doc-comment line for this function, if present.
     pub doc: Option<String>,
     /// Parameter list as rendered strings ("x: i32", "y: String", ...)
     pub params: Vec<String>,
     /// Return type as rendered string ("usize", "Result<T>", ...)
     pub ret: Option<String>,
+    /// Trait method without a default body.
+    pub is_abstract: bool,
+    /// Calls made in the function body, in source order, nested in the
+    /// branches and loops that guard them.
+    pub body: Vec<Step>,
+    /// Local types built in the body (`Type { .. }`, `Type(..)`, a unit
+    /// `Type`, an enum's `Type::Variant`), resolved to their full path.
+    pub constructs: BTreeSet<String>,
+    /// Local types named in the parameter and return types, resolved to
+    /// their full path.
+    pub uses: BTreeSet<String>,
+    /// Set for test functions.
+    pub test: Option<TestInfo>,
+    /// Trait this method implements, for methods of `impl Trait for Type`.
+    pub trait_impl: Option<String>,
+}
+
+/// How a test function is declared.
+#[derive(Debug, Clone, Default)]
+pub struct TestInfo {
+    /// Attribute or macro marking it a test (`test`, `tokio::test`, `proptest!`).
+    pub marker: String,
+    /// `#[ignore]`
+    pub ignored: bool,
+    /// `#[should_panic]`
+    pub should_panic: bool,
+    /// Number of `#[test_case(..)]` / rstest `#[case(..)]` cases; 0 when the
+    /// test is not parameterized.
+    pub cases: usize,
+}
+
+/// Attributes (and `name!` test macros) recognised as marking a test.
+pub const DEFAULT_TEST_ATTRIBUTES: &[&str] = &[
+    "test",
+    "tokio::test",
+    "async_std::test",
+    "actix_rt::test",
+    "sqlx::test",
+    "test_log::test",
+    "rstest",
+    "test_case",
+    "wasm_bindgen_test",
+    "bench",
+    "proptest!",
+];
+
+/// One call-relevant step of a function body.
+pub enum Step {
+    Call(Call),
+    /// `if` / `match`: one `(condition or pattern, steps)` pair per branch;
+    /// a plain `else` has an empty label.
+    Branch(Vec<(String, Vec<Step>)>),
+    /// `for` / `while` / `loop` with its header as label.
+    Loop(String, Vec<Step>),
+}
+
+/// How a call site names the function it calls.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Callee {
+    /// `foo()`, `a::foo()`, `Type::new()`, `Self::new()`
+    Path(String),
+    /// `self.method()`
+    SelfMethod(String),
+    /// `value.method()` on any other receiver
+    Method(String),
+}
+
+pub struct Call {
+    pub callee: Callee,
+    /// Type of the receiver of a `value.method()` call as written, when the
+    /// parameter or `let` binding it names declares it.
+    pub receiver: Option<String>,
+    /// Full path of the called project function, when it could be resolved.
+    pub target: Option<String>,
+}
+
+impl FunctionInfo {
+    /// Every call in the body, flattened out of branches and loops.
+    pub fn calls(&self) -> Vec<&Call> {
+        fn walk<'a>(steps: &'a [Step], out: &mut Vec<&'a Call>) {
+            for step in steps {
+                match step {
+                    Step::Call(call) => out.push(call),
+                    Step::Branch(branches) => {
+                        for (_, steps) in branches {
+                            walk(steps, out);
+                        }
+                    }
+                    Step::Loop(_, steps) => walk(steps, out),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.body, &mut out);
+        out
+    }
+
+    fn calls_mut(&mut self) -> Vec<&mut Call> {
+        fn walk<'a>(steps: &'a mut [Step], out: &mut Vec<&'a mut Call>) {
+            for step in steps {
+                match step {
+                    Step::Call(call) => out.push(call),
+                    Step::Branch(branches) => {
+                        for (_, steps) in branches {
+                            walk(steps, out);
+                        }
+                    }
+                    Step::Loop(_, steps) => walk(steps, out),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&mut self.body, &mut out);
+        out
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -35,328 +229,1792 @@ pub struct Relationship {
     pub source: String,
     pub target: String,
     pub edge_type: String,
+    /// Multiplicity at the target end (`0..1`, `*`).
+    pub multiplicity: Option<String>,
+    /// The target is a trait held as `dyn Trait` / `impl Trait`.
+    pub trait_object: bool,
     pub label: Option<String>,
+    /// Visibility of the field the relationship comes from.
+    pub visibility: Visibility,
 }
 
 pub struct ParsedProject {
+    /// Classes keyed by their full module path (see [`Class::path`]).
     pub classes: HashMap<String, Class>,
-    pub file_functions_main: HashMap<String, Vec<FunctionInfo>>,
-    pub file_functions_tests: HashMap<String, Vec<FunctionInfo>>,
+    pub file_functions_main: BTreeMap<String, Vec<FunctionInfo>>,
+    pub file_functions_tests: BTreeMap<String, Vec<FunctionInfo>>,
+    /// Module-level consts and statics per file, like the free functions.
+    pub file_consts_main: BTreeMap<String, Vec<Constant>>,
+    pub file_consts_tests: BTreeMap<String, Vec<Constant>>,
+    /// Full path of every module, including inline `mod x { }` blocks.
+    pub modules: BTreeSet<String>,
+    /// Module -> module it depends on -> items it references there, from
+    /// `use` declarations and qualified paths.
+    pub module_deps: BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+    /// Problems found while walking the module tree (missing module files,
+    /// `.rs` files no `mod` reaches).
+    pub warnings: Vec<String>,
+}
+
+/// One crate handed to [`parse_crates`].
+pub struct CrateSource {
+    /// Root module of the crate (`crate`, or its name in a workspace).
+    pub root: String,
+    /// Source directory, or the crate's root file when it is not `lib.rs` /
+    /// `main.rs` (`[lib] path`).
+    pub src: PathBuf,
+    /// Names the crate's code uses for other crates that differ from their
+    /// root module (renamed dependencies, or the package name binaries use
+    /// for their library) -> that root module.
+    pub aliases: BTreeMap<String, String>,
 }
 
-/// Public API: parse all Rust files under `src_dir` and build our model.
+/// Public API: parse the crate whose sources are in `src_dir`, following its
+/// module tree, and build our model.
 pub fn parse_project(src_dir: &Path) -> anyhow::Result<ParsedProject> {
+    parse_crates(
+        &[CrateSource {
+            root: CRATE_ROOT.to_string(),
+            src: src_dir.to_path_buf(),
+            aliases: BTreeMap::new(),
+        }],
+        DEFAULT_TEST_ATTRIBUTES,
+    )
+}
+
+/// Parse several crates into one model: each crate's modules live under its
+/// root, and paths that start with another crate's name (or an alias of it)
+/// resolve into it.
+///
+/// With more than one crate, file keys are prefixed with the crate name
+/// (`core/net/mod`) so equally named files stay apart. Functions carrying one
+/// of `test_attributes` (see [`DEFAULT_TEST_ATTRIBUTES`]) are tests.
+pub fn parse_crates(
+    crates: &[CrateSource],
+    test_attributes: &[&str],
+) -> anyhow::Result<ParsedProject> {
     let mut parser = Parser::new();
     parser.set_language(&tree_sitter_rust::language())?;
 
     let mut classes: HashMap<String, Class> = HashMap::new();
-    let mut file_functions_main: HashMap<String, Vec<FunctionInfo>> = HashMap::new();
-    let mut file_functions_tests: HashMap<String, Vec<FunctionInfo>> = HashMap::new();
-    let mut local_types = HashSet::new();
+    let mut file_functions_main: BTreeMap<String, Vec<FunctionInfo>> = BTreeMap::new();
+    let mut file_functions_tests: BTreeMap<String, Vec<FunctionInfo>> = BTreeMap::new();
+    let mut file_consts_main: BTreeMap<String, Vec<Constant>> = BTreeMap::new();
+    let mut file_consts_tests: BTreeMap<String, Vec<Constant>> = BTreeMap::new();
+    let mut module_deps = BTreeMap::new();
+    let mut resolver = Resolver::default();
+    let mut warnings = Vec::new();
 
-    // FIRST PASS: collect all local types
-    for file in rust_files(src_dir) {
-        let content = fs::read_to_string(&file)?;
-        let tree = parser.parse(&content, None).unwrap();
-        collect_type_names(tree.root_node(), &content, &mut local_types);
+    // FIRST PASS: collect all local types, modules and imports
+    let mut files = Vec::new();
+    for source in crates {
+        let root = &source.root;
+        resolver.add_item(root.clone(), ItemKind::Module);
+        resolver.add_crate_root(root.clone(), source.aliases.clone());
+        for file in crate_files(&mut parser, root, &source.src, &mut warnings)? {
+            if file.crate_root != *root && file.module == file.crate_root {
+                resolver.add_binary_root(file.crate_root.clone(), source.aliases.clone());
+            }
+            let tree = parser.parse(&file.content, None).unwrap();
+            resolver.add_item(file.module.clone(), ItemKind::Module);
+            collect_declarations(tree.root_node(), &file.content, &file.module, &mut resolver);
+            files.push((root, &source.src, file));
+        }
     }
 
     // SECOND PASS: extract items
-    for file in rust_files(src_dir) {
-        let content = fs::read_to_string(&file)?;
-        let tree = parser.parse(&content, None).unwrap();
-
-        let rel_path = file
-            .strip_prefix(src_dir)
-            .unwrap_or(&file)
-            .to_string_lossy()
-            .to_string();
+    for (root, src, file) in &files {
+        let tree = parser.parse(&file.content, None).unwrap();
 
-        let file_module = Path::new(&rel_path)
-            .with_extension("")
-            .to_string_lossy()
-            .to_string();
+        // File keys stay relative to the source directory when the crate is
+        // given by its root file.
+        let src_dir = if src.is_file() {
+            src.parent().unwrap_or(src)
+        } else {
+            src
+        };
+        let rel_path = file.path.strip_prefix(src_dir).unwrap_or(&file.path);
+        let mut file_module = rel_path.with_extension("").to_string_lossy().to_string();
+        if crates.len() > 1 {
+            file_module = format!("{root}/{file_module}");
+        }
 
-        extract_items(
-            tree.root_node(),
-            &content,
-            &rel_path,
-            &file_module,
-            &local_types,
-            &mut classes,
-            &mut file_functions_main,
-            &mut file_functions_tests,
-        );
+        Extractor {
+            src: &file.content,
+            file_module: &file_module,
+            module: file.module.clone(),
+            inline_module: String::new(),
+            in_test: file.is_test,
+            test_attributes,
+            resolver: &resolver,
+            classes: &mut classes,
+            file_functions_main: &mut file_functions_main,
+            file_functions_tests: &mut file_functions_tests,
+            file_consts_main: &mut file_consts_main,
+            file_consts_tests: &mut file_consts_tests,
+            module_deps: &mut module_deps,
+        }
+        .extract_items(tree.root_node());
+    }
+
+    // THIRD PASS: resolve calls now that every function and method is known
+    let known: HashSet<String> = file_functions_main
+        .values()
+        .chain(file_functions_tests.values())
+        .flatten()
+        .chain(classes.values().flat_map(|c| &c.methods))
+        .map(|f| f.path.clone())
+        .collect();
+
+    for f in file_functions_main
+        .values_mut()
+        .chain(file_functions_tests.values_mut())
+        .flatten()
+    {
+        let module = f.module.clone();
+        for call in f.calls_mut() {
+            call.target = resolve_call(&resolver, &known, &module, None, call);
+        }
+    }
+    for class in classes.values_mut() {
+        for m in &mut class.methods {
+            let module = m.module.clone();
+            for call in m.calls_mut() {
+                call.target = resolve_call(&resolver, &known, &module, Some(&class.path), call);
+            }
+        }
     }
 
     Ok(ParsedProject {
         classes,
         file_functions_main,
         file_functions_tests,
+        file_consts_main,
+        file_consts_tests,
+        modules: resolver.modules().cloned().collect(),
+        module_deps,
+        warnings,
     })
 }
 
-fn rust_files(path: &Path) -> Vec<PathBuf> {
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().extension().map(|e| e == "rs").unwrap_or(false))
-        .map(|e| e.path().to_path_buf())
-        .collect()
-}
-
-fn collect_type_names(node: Node, src: &str, out: &mut HashSet<String>) {
+fn collect_declarations(node: Node, src: &str, module: &str, out: &mut Resolver) {
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
         match child.kind() {
-            "struct_item" | "enum_item" | "trait_item" => {
+            "struct_item" | "enum_item" | "union_item" | "type_item" | "trait_item" => {
                 if let Some(name) = child.child_by_field_name("name") {
-                    out.insert(name.utf8_text(src.as_bytes()).unwrap().to_string());
+                    let name = name.utf8_text(src.as_bytes()).unwrap();
+                    out.add_item(format!("{module}::{name}"), ItemKind::Type);
+                }
+                if child.kind() == "trait_item" {
+                    continue;
                 }
             }
+            "function_item" => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    let name = name.utf8_text(src.as_bytes()).unwrap();
+                    out.add_item(format!("{module}::{name}"), ItemKind::Function);
+                }
+                // Items in the body are local to it, but its imports are
+                // taken as the module's for resolving the body's paths.
+                if let Some(body) = child.child_by_field_name("body") {
+                    collect_body_uses(body, src, module, out);
+                }
+                continue;
+            }
+            // Methods are not module items; they are resolved through their type.
+            "impl_item" => continue,
+            "use_declaration" => {
+                out.add_use(module, child, src);
+                if visibility_of(child, src) == Visibility::Public {
+                    out.add_reexport(module, child, src);
+                }
+                continue;
+            }
+            "mod_item" => {
+                if let Some(name) = child.child_by_field_name("name") {
+                    let name = name.utf8_text(src.as_bytes()).unwrap();
+                    let path = format!("{module}::{name}");
+                    out.add_item(path.clone(), ItemKind::Module);
+                    if visibility_of(child, src) == Visibility::Public {
+                        out.add_public_module(path.clone());
+                    }
+                    if let Some(body) = child.child_by_field_name("body") {
+                        collect_declarations(body, src, &path, out);
+                    }
+                }
+                continue;
+            }
             _ => {}
         }
-        collect_type_names(child, src, out);
+        collect_declarations(child, src, module, out);
     }
 }
 
-fn has_test_attribute(node: Node, src: &str) -> bool {
-    let bytes = src.as_bytes();
+/// `use` declarations anywhere inside a function body, recorded on `module`.
+fn collect_body_uses(node: Node, src: &str, module: &str, out: &mut Resolver) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind() == "use_declaration" {
+            out.add_use(module, child, src);
+        } else {
+            collect_body_uses(child, src, module, out);
+        }
+    }
+}
 
-    let mut cur = match node.prev_sibling() {
-        Some(n) => n,
-        None => return false,
-    };
+/// Per-file state threaded through `extract_items`.
+struct Extractor<'a> {
+    src: &'a str,
+    file_module: &'a str,
+    /// Module path of the item currently being visited ("crate::net").
+    module: String,
+    /// Inline `mod` blocks entered so far inside this file ("a::b").
+    inline_module: String,
+    /// Inside a `#[cfg(test)]` module: every item goes to the tests diagram.
+    in_test: bool,
+    /// Attribute paths (and `name!` macros) that mark a test.
+    test_attributes: &'a [&'a str],
+    resolver: &'a Resolver,
+    classes: &'a mut HashMap<String, Class>,
+    file_functions_main: &'a mut BTreeMap<String, Vec<FunctionInfo>>,
+    file_functions_tests: &'a mut BTreeMap<String, Vec<FunctionInfo>>,
+    file_consts_main: &'a mut BTreeMap<String, Vec<Constant>>,
+    file_consts_tests: &'a mut BTreeMap<String, Vec<Constant>>,
+    module_deps: &'a mut BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+}
 
-    loop {
-        match cur.kind() {
-            "attribute_item" | "attribute" => {
-                if let Ok(text) = cur.utf8_text(bytes) {
-                    if text.contains("#[test]") || text.contains("test]") {
-                        return true;
+impl Extractor<'_> {
+    fn extract_items(&mut self, node: Node) {
+        let src = self.src;
+        let file_module = self.file_module;
+        let module_level = matches!(node.kind(), "source_file" | "declaration_list");
+        let mut cursor = node.walk();
+
+        for child in node.children(&mut cursor) {
+            match child.kind() {
+                // Items in function bodies are not part of the module.
+                "struct_item" | "union_item" | "enum_item" | "type_item" | "const_item"
+                | "static_item" | "trait_item" | "function_item" | "impl_item" | "mod_item"
+                    if !module_level =>
+                {
+                    continue;
+                }
+
+                "struct_item" | "union_item" => {
+                    self.extract_struct(child);
+                    self.extract_derives(child);
+                }
+
+                "enum_item" => {
+                    self.extract_enum(child);
+                    self.extract_derives(child);
+                }
+
+                "type_item" => self.extract_type_alias(child),
+
+                "const_item" | "static_item" => {
+                    if let Some(mut constant) = constant(child, src) {
+                        constant.inline_module = self.inline_module.clone();
+                        constant.exported = constant.visibility == Visibility::Public
+                            && self
+                                .resolver
+                                .is_exported(&format!("{}::{}", self.module, constant.name));
+                        let target = if self.in_test {
+                            &mut *self.file_consts_tests
+                        } else {
+                            &mut *self.file_consts_main
+                        };
+                        target.entry(file_module.into()).or_default().push(constant);
                     }
                 }
-            }
-            "line_comment" | "block_comment" => {}
-            _ => {
-                let text = cur.utf8_text(bytes).unwrap_or("");
-                if !text.trim().is_empty() {
-                    break;
+
+                "trait_item" => {
+                    // Trait methods are listed on the trait itself.
+                    self.extract_trait(child);
+                    continue;
+                }
+
+                "function_item" => {
+                    if let Some(mut info) = function_info(child, src, &self.module, &self.module) {
+                        info.constructs = self.constructs(child, None);
+                        info.uses = signature_types(child, &[], src, &self.module, self.resolver);
+                        info.test = test_info(&outer_attributes(child, src), self.test_attributes);
+                        info.inline_module = self.inline_module.clone();
+                        info.exported = info.visibility == Visibility::Public
+                            && self.resolver.is_exported(&info.path);
+                        let target = if self.in_test || info.test.is_some() {
+                            &mut *self.file_functions_tests
+                        } else {
+                            &mut *self.file_functions_main
+                        };
+                        target.entry(file_module.into()).or_default().push(info);
+                    }
                 }
+
+                "macro_invocation" => {
+                    let mut tests = macro_tests(child, src, &self.module, self.test_attributes);
+                    if !tests.is_empty() {
+                        for test in &mut tests {
+                            test.inline_module = self.inline_module.clone();
+                        }
+                        self.file_functions_tests
+                            .entry(file_module.into())
+                            .or_default()
+                            .extend(tests);
+                        continue;
+                    }
+                }
+
+                "impl_item" => {
+                    // Methods belong to the implementing type, not to the
+                    // per-file `_functions` pseudo-class.
+                    self.extract_impl(child);
+                    continue;
+                }
+
+                "use_declaration" => {
+                    for path in use_paths(child, src) {
+                        self.record_module_ref(&path);
+                    }
+                    continue;
+                }
+
+                "scoped_identifier" | "scoped_type_identifier" => {
+                    self.record_module_ref(child.utf8_text(src.as_bytes()).unwrap());
+                    continue;
+                }
+
+                "mod_item" => {
+                    if let (Some(name), Some(body)) = (
+                        child.child_by_field_name("name"),
+                        child.child_by_field_name("body"),
+                    ) {
+                        let name = name.utf8_text(src.as_bytes()).unwrap();
+                        let parent = self.module.clone();
+                        let parent_inline = self.inline_module.clone();
+                        let was_test = self.in_test;
+                        self.module = format!("{parent}::{name}");
+                        self.inline_module = match parent_inline.as_str() {
+                            "" => name.to_string(),
+                            outer => format!("{outer}::{name}"),
+                        };
+                        self.in_test |= is_cfg_test(&outer_attributes(child, src));
+                        self.extract_items(body);
+                        self.module = parent;
+                        self.inline_module = parent_inline;
+                        self.in_test = was_test;
+                    }
+                    continue;
+                }
+
+                _ => {}
             }
+
+            self.extract_items(child);
         }
+    }
 
-        match cur.prev_sibling() {
-            Some(prev) => cur = prev,
-            None => break,
+    fn extract_struct(&mut self, node: Node) {
+        let src = self.src;
+        let file_module = self.file_module;
+        let name = node
+            .child_by_field_name("name")
+            .unwrap()
+            .utf8_text(src.as_bytes())
+            .unwrap()
+            .to_string();
+
+        let doc = leading_doc_comment(node, src);
+
+        let fields = node
+            .child_by_field_name("body")
+            .map(|body| struct_fields(body, src))
+            .unwrap_or_default();
+        // `struct Meters(f64);`
+        let stereotype = match node.child_by_field_name("body") {
+            _ if node.kind() == "union_item" => "union",
+            Some(body) if body.kind() == "ordered_field_declaration_list" && fields.len() == 1 => {
+                "newtype"
+            }
+            _ => "struct",
+        };
+
+        let path = format!("{}::{}", self.module, name);
+        let visibility = visibility_of(node, src);
+        let (generics, bounds) = self.type_parameters(node);
+        let Some(class) = declare(
+            self.classes,
+            Class {
+                name: name.clone(),
+                path: path.clone(),
+                file: file_module.into(),
+                inline_module: self.inline_module.clone(),
+                visibility,
+                exported: visibility == Visibility::Public && self.resolver.is_exported(&path),
+                stereotype: Some(stereotype.into()),
+                doc,
+                is_test: self.in_test,
+                generics,
+                repr: repr(&outer_attributes(node, src)),
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+        class.relationships.extend(bounds);
+
+        for (field_name, ftype, visibility) in fields {
+            class.fields.push(Field {
+                name: field_name.clone(),
+                ty: Some(compact_text(ftype, src)),
+                visibility,
+                discriminant: None,
+            });
+
+            for link in type_links(ftype, src) {
+                if is_type_parameter(&class.generics, &link.path) {
+                    continue;
+                }
+                if let Some(target) = self.resolver.resolve_type(&self.module, &link.path) {
+                    let label = match &link.key {
+                        Some(key) => format!("{} {}[{}]", link.display(), field_name, key),
+                        None => format!("{} {}", link.display(), field_name),
+                    };
+                    class.relationships.insert(Relationship {
+                        source: class.path.clone(),
+                        target,
+                        edge_type: link.edge.into(),
+                        multiplicity: link.multiplicity.map(Into::into),
+                        trait_object: link.trait_object,
+                        label: Some(label),
+                        visibility,
+                    });
+                }
+            }
         }
     }
 
-    false
-}
+    fn extract_enum(&mut self, node: Node) {
+        let src = self.src;
+        let file_module = self.file_module;
+        let name = node
+            .child_by_field_name("name")
+            .unwrap()
+            .utf8_text(src.as_bytes())
+            .unwrap()
+            .to_string();
 
-fn extract_items(
-    node: Node,
-    src: &str,
-    file_name: &str,
-    file_module: &str,
-    local_types: &HashSet<String>,
-    classes: &mut HashMap<String, Class>,
-    file_functions_main: &mut HashMap<String, Vec<FunctionInfo>>,
-    file_functions_tests: &mut HashMap<String, Vec<FunctionInfo>>,
-) {
-    let mut cursor = node.walk();
+        let doc = leading_doc_comment(node, src);
 
-    for child in node.children(&mut cursor) {
-        match child.kind() {
-            "struct_item" => {
-                let name = child
-                    .child_by_field_name("name")
-                    .unwrap()
-                    .utf8_text(src.as_bytes())
-                    .unwrap()
-                    .to_string();
-
-                let doc = leading_doc_comment(child, src);
-
-                let class = classes.entry(name.clone()).or_insert_with(|| Class {
-                    name: name.clone(),
-                    file: file_module.into(),
-                    stereotype: Some("struct".into()),
-                    doc,
-                    ..Default::default()
-                });
+        // Variants are as visible as the enum itself.
+        let visibility = visibility_of(node, src);
+        let attrs = outer_attributes(node, src);
 
-                if let Some(body) = child.child_by_field_name("body") {
-                    let mut c = body.walk();
-                    for field in body.children(&mut c) {
-                        if field.kind() == "field_declaration" {
-                            let field_name = field
-                                .child_by_field_name("name")
-                                .and_then(|n| n.utf8_text(src.as_bytes()).ok())
-                                .map(|s| s.to_string());
-
-                            if let Some(ref fname) = field_name {
-                                class.fields.push(fname.clone());
-                            }
+        let path = format!("{}::{}", self.module, name);
+        let (generics, bounds) = self.type_parameters(node);
+        let Some(class) = declare(
+            self.classes,
+            Class {
+                name: name.clone(),
+                path: path.clone(),
+                file: file_module.into(),
+                inline_module: self.inline_module.clone(),
+                visibility,
+                exported: visibility == Visibility::Public && self.resolver.is_exported(&path),
+                stereotype: Some("enum".into()),
+                doc,
+                is_test: self.in_test,
+                generics,
+                repr: repr(&attrs),
+                non_exhaustive: attrs.iter().any(|a| a.path == "non_exhaustive"),
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+        class.relationships.extend(bounds);
+
+        if let Some(body) = node.child_by_field_name("body") {
+            let mut c = body.walk();
+            for variant in body.children(&mut c) {
+                if variant.kind() == "enum_variant" {
+                    let variant_name = variant
+                        .child_by_field_name("name")
+                        .and_then(|n| n.utf8_text(src.as_bytes()).ok())
+                        .map(|s| s.to_string());
+
+                    let payload = variant
+                        .child_by_field_name("body")
+                        .map(|vbody| struct_fields(vbody, src))
+                        .unwrap_or_default();
+                    let positional = variant
+                        .child_by_field_name("body")
+                        .is_some_and(|b| b.kind() == "ordered_field_declaration_list");
 
-                            if let Some(ftype) = field.child_by_field_name("type") {
-                                for ty in extract_type_identifiers(ftype, src) {
-                                    if local_types.contains(&ty) {
-                                        let edge = if ftype.kind() == "reference_type" {
-                                            "o--"
-                                        } else {
-                                            "*--"
-                                        };
-
-                                        let label = field_name
-                                            .as_ref()
-                                            .map(|fname| format!("{} {}", ty, fname));
-
-                                        class.relationships.insert(Relationship {
-                                            source: class.name.clone(),
-                                            target: ty,
-                                            edge_type: edge.into(),
-                                            label,
-                                        });
-                                    }
+                    if let Some(ref vname) = variant_name {
+                        // `Rect(Point, Point)` -> "Point, Point",
+                        // `Circle { r: f64 }` -> "f64 r".
+                        let types: Vec<String> = payload
+                            .iter()
+                            .map(|(field_name, ftype, _)| {
+                                let ty = compact_text(*ftype, src);
+                                if positional {
+                                    ty
+                                } else {
+                                    format!("{} {}", ty, field_name)
                                 }
+                            })
+                            .collect();
+                        class.fields.push(Field {
+                            name: vname.clone(),
+                            ty: (!types.is_empty()).then(|| types.join(", ")),
+                            visibility,
+                            discriminant: variant
+                                .child_by_field_name("value")
+                                .map(|v| compact_text(v, src)),
+                        });
+                    }
+
+                    for (field_name, ftype, _) in payload {
+                        for link in type_links(ftype, src) {
+                            if is_type_parameter(&class.generics, &link.path) {
+                                continue;
+                            }
+                            if let Some(target) =
+                                self.resolver.resolve_type(&self.module, &link.path)
+                            {
+                                let ty = link.display();
+
+                                // `Click(Point)` -> "Point Click",
+                                // `Move { to: Point }` -> "Point Move::to".
+                                let label = match &variant_name {
+                                    Some(vn) if positional => Some(format!("{} {}", ty, vn)),
+                                    Some(vn) => Some(format!("{} {}::{}", ty, vn, field_name)),
+                                    None => None,
+                                };
+
+                                class.relationships.insert(Relationship {
+                                    source: class.path.clone(),
+                                    target,
+                                    edge_type: link.edge.into(),
+                                    multiplicity: link.multiplicity.map(Into::into),
+                                    trait_object: link.trait_object,
+                                    label,
+                                    visibility,
+                                });
                             }
                         }
                     }
                 }
             }
+        }
+    }
 
-            "enum_item" => {
-                let name = child
-                    .child_by_field_name("name")
-                    .unwrap()
-                    .utf8_text(src.as_bytes())
-                    .unwrap()
-                    .to_string();
+    /// `type Name<T> = Aliased;` as a `<<type alias>>` class with a `..>` edge
+    /// to every local type in the aliased type.
+    fn extract_type_alias(&mut self, node: Node) {
+        let src = self.src;
+        let (Some(name), Some(aliased)) = (
+            node.child_by_field_name("name")
+                .and_then(|n| n.utf8_text(src.as_bytes()).ok()),
+            node.child_by_field_name("type"),
+        ) else {
+            return;
+        };
 
-                let doc = leading_doc_comment(child, src);
+        let path = format!("{}::{}", self.module, name);
+        let visibility = visibility_of(node, src);
+        let (generics, _) = self.type_parameters(node);
+        let targets: Vec<String> = type_links(aliased, src)
+            .into_iter()
+            .filter(|link| !is_type_parameter(&generics, &link.path))
+            .filter_map(|link| self.resolver.resolve_type(&self.module, &link.path))
+            .collect();
 
-                let class = classes.entry(name.clone()).or_insert_with(|| Class {
-                    name: name.clone(),
-                    file: file_module.into(),
-                    stereotype: Some("enum".into()),
-                    doc,
-                    ..Default::default()
-                });
+        let Some(class) = declare(
+            self.classes,
+            Class {
+                name: name.to_string(),
+                path: path.clone(),
+                file: self.file_module.into(),
+                inline_module: self.inline_module.clone(),
+                visibility,
+                exported: visibility == Visibility::Public && self.resolver.is_exported(&path),
+                stereotype: Some("type alias".into()),
+                doc: leading_doc_comment(node, src),
+                is_test: self.in_test,
+                generics,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+        for target in targets.into_iter().filter(|t| *t != path) {
+            class.relationships.insert(Relationship {
+                source: path.clone(),
+                target,
+                edge_type: "..>".into(),
+                multiplicity: None,
+                trait_object: false,
+                label: Some("alias".into()),
+                visibility,
+            });
+        }
+    }
 
-                if let Some(body) = child.child_by_field_name("body") {
-                    let mut c = body.walk();
-                    for variant in body.children(&mut c) {
-                        if variant.kind() == "enum_variant" {
-                            let variant_name = variant
-                                .child_by_field_name("name")
-                                .and_then(|n| n.utf8_text(src.as_bytes()).ok())
-                                .map(|s| s.to_string());
-
-                            if let Some(ref vname) = variant_name {
-                                class.fields.push(vname.clone());
-                            }
+    /// Record the `#[derive(...)]` traits of a type as trait impls.
+    fn extract_derives(&mut self, node: Node) {
+        let Some(name) = node
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(self.src.as_bytes()).ok())
+        else {
+            return;
+        };
+        let path = format!("{}::{}", self.module, name);
+        for derive in derives(&outer_attributes(node, self.src)) {
+            let trait_name = self.trait_path(&derive);
+            let class = self.class_entry(&path);
+            if class.trait_impls.insert(trait_name.clone()) {
+                class.derives.push(trait_name);
+            }
+        }
+    }
 
-                            if let Some(vbody) = variant.child_by_field_name("body") {
-                                let mut vc = vbody.walk();
-                                for field in vbody.children(&mut vc) {
-                                    if field.kind() == "field_declaration" {
-                                        let field_name = field
-                                            .child_by_field_name("name")
-                                            .and_then(|n| n.utf8_text(src.as_bytes()).ok())
-                                            .map(|s| s.to_string());
-
-                                        if let Some(ftype) = field.child_by_field_name("type") {
-                                            for ty in extract_type_identifiers(ftype, src) {
-                                                if local_types.contains(&ty) {
-                                                    let edge = if ftype.kind() == "reference_type" {
-                                                        "o--"
-                                                    } else {
-                                                        "*--"
-                                                    };
-
-                                                    let label = match (&variant_name, &field_name) {
-                                                        (Some(vn), Some(fn_)) => {
-                                                            Some(format!("{} {}::{}", ty, vn, fn_))
-                                                        }
-                                                        (Some(vn), None) => {
-                                                            Some(format!("{} {}", ty, vn))
-                                                        }
-                                                        _ => None,
-                                                    };
-
-                                                    class.relationships.insert(Relationship {
-                                                        source: class.name.clone(),
-                                                        target: ty,
-                                                        edge_type: edge.into(),
-                                                        label,
-                                                    });
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+    fn extract_trait(&mut self, node: Node) {
+        let src = self.src;
+        let bytes = src.as_bytes();
+        let Some(name) = node
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(bytes).ok())
+            .map(str::to_string)
+        else {
+            return;
+        };
+
+        let mut supertraits = BTreeSet::new();
+        if let Some(bounds) = node.child_by_field_name("bounds") {
+            self.record_path_refs(bounds);
+            let mut c = bounds.walk();
+            for bound in bounds.named_children(&mut c) {
+                supertraits.extend(self.trait_ref(bound));
+            }
+        }
+
+        let doc = leading_doc_comment(node, src);
+        let file_module = self.file_module;
+        // Trait items are as visible as the trait itself.
+        let visibility = visibility_of(node, src);
+        let path = format!("{}::{}", self.module, name);
+        let (generics, bounds) = self.type_parameters(node);
+        let Some(class) = declare(
+            self.classes,
+            Class {
+                name: name.clone(),
+                path: path.clone(),
+                file: file_module.into(),
+                inline_module: self.inline_module.clone(),
+                visibility,
+                exported: visibility == Visibility::Public && self.resolver.is_exported(&path),
+                stereotype: Some("interface".into()),
+                doc,
+                is_test: self.in_test,
+                generics,
+                ..Default::default()
+            },
+        ) else {
+            return;
+        };
+        class.supertraits.extend(supertraits);
+        class.relationships.extend(bounds);
+
+        let Some(body) = node.child_by_field_name("body") else {
+            return;
+        };
+
+        let mut c = body.walk();
+        for item in body.children(&mut c) {
+            match item.kind() {
+                "function_item" | "function_signature_item" => {
+                    if let Some(mut info) = function_info(item, src, &self.module, &path) {
+                        info.uses = signature_types(
+                            item,
+                            &class.generics,
+                            src,
+                            &self.module,
+                            self.resolver,
+                        );
+                        class.methods.push(FunctionInfo {
+                            visibility,
+                            exported: class.exported,
+                            inline_module: self.inline_module.clone(),
+                            ..info
+                        });
                     }
                 }
+                // `type Item: Clone;` / `const MAX: usize;`
+                "associated_type" | "const_item" => {
+                    if let Ok(text) = item.utf8_text(bytes) {
+                        let decl = text.trim_end_matches(';');
+                        let decl = decl.split_once('=').map_or(decl, |(lhs, _)| lhs);
+                        class.fields.push(Field {
+                            name: decl.trim().to_string(),
+                            ty: None,
+                            visibility,
+                            discriminant: None,
+                        });
+                    }
+                }
+                _ => {}
             }
+        }
 
-            "function_item" => {
-                if let Some(name_node) = child.child_by_field_name("name") {
-                    let func_name = name_node.utf8_text(src.as_bytes()).unwrap().to_string();
-                    let doc = leading_doc_comment(child, src);
-                    let params = extract_function_params(child, src);
-                    let ret = extract_function_return_type(child, src);
-
-                    let info = FunctionInfo {
-                        name: func_name,
-                        doc,
-                        params,
-                        ret,
-                    };
+        let mut c = body.walk();
+        for item in body.children(&mut c) {
+            self.extract_items(item);
+        }
+    }
 
-                    if has_test_attribute(child, src) {
-                        file_functions_tests
-                            .entry(file_module.into())
-                            .or_default()
-                            .push(info);
-                    } else {
-                        file_functions_main
-                            .entry(file_module.into())
-                            .or_default()
-                            .push(info);
+    fn extract_impl(&mut self, impl_node: Node) {
+        let src = self.src;
+        let Some(body) = impl_node.child_by_field_name("body") else {
+            return;
+        };
+
+        for field in ["trait", "type"] {
+            if let Some(n) = impl_node.child_by_field_name(field) {
+                self.record_path_refs(n);
+            }
+        }
+
+        let target = impl_node
+            .child_by_field_name("type")
+            .and_then(|ty| base_type_path(ty, src))
+            .and_then(|ty| self.resolver.resolve_type(&self.module, &ty));
+        let trait_name = impl_node
+            .child_by_field_name("trait")
+            .and_then(|tr| self.trait_ref(tr));
+
+        if let Some(name) = &target {
+            let module = self.module.clone();
+            self.class_entry(name).impl_modules.insert(module);
+        }
+        if let (Some(name), Some(trait_name)) = (&target, &trait_name) {
+            self.class_entry(name)
+                .trait_impls
+                .insert(trait_name.clone());
+        }
+
+        let impl_generics = type_parameter_names(impl_node, src);
+        let mut cursor = body.walk();
+        for item in body.children(&mut cursor) {
+            // Trait impl methods are kept for their calls but marked, since the
+            // impl itself is drawn as a realization edge.
+            if item.kind() == "function_item"
+                && let Some(name) = &target
+                && let Some(mut info) = function_info(item, src, &self.module, name)
+            {
+                info.constructs = self.constructs(item, Some(name));
+                info.uses = signature_types(item, &impl_generics, src, &self.module, self.resolver);
+                info.inline_module = self.inline_module.clone();
+                info.trait_impl = trait_name.clone();
+                info.exported =
+                    info.visibility == Visibility::Public && self.resolver.is_exported(name);
+                self.class_entry(name).methods.push(info);
+            }
+
+            self.extract_items(item);
+        }
+    }
+
+    /// Parameter names of a generic type or trait, and `..>` dependency edges
+    /// to the local traits its parameter bounds and `where` clause name.
+    fn type_parameters(&self, node: Node) -> (Vec<String>, Vec<Relationship>) {
+        let bytes = self.src.as_bytes();
+        let Some(path) = node
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(bytes).ok())
+            .map(|name| format!("{}::{}", self.module, name))
+        else {
+            return (Vec::new(), Vec::new());
+        };
+
+        let generics = type_parameter_names(node, self.src);
+        // (bounded type as written, bounds node)
+        let mut bounded: Vec<(String, Node)> = declared_type_parameters(node)
+            .into_iter()
+            .filter_map(|(name, bounds)| Some((compact_text(name, self.src), bounds?)))
+            .collect();
+
+        let mut c = node.walk();
+        for clause in node.children(&mut c).filter(|n| n.kind() == "where_clause") {
+            let mut wc = clause.walk();
+            for predicate in clause.named_children(&mut wc) {
+                if let (Some(left), Some(bounds)) = (
+                    predicate.child_by_field_name("left"),
+                    predicate.child_by_field_name("bounds"),
+                ) {
+                    bounded.push((compact_text(left, self.src), bounds));
+                }
+            }
+        }
+
+        let visibility = visibility_of(node, self.src);
+        let mut edges = Vec::new();
+        for (param, bounds) in bounded {
+            let mut bc = bounds.walk();
+            for bound in bounds.named_children(&mut bc) {
+                let Some(target) = base_type_path(bound, self.src)
+                    .and_then(|b| self.resolver.resolve_type(&self.module, &b))
+                else {
+                    continue;
+                };
+                edges.push(Relationship {
+                    source: path.clone(),
+                    label: Some(format!("{param}: {}", short_name(&target))),
+                    target,
+                    edge_type: "..>".into(),
+                    multiplicity: None,
+                    trait_object: false,
+                    visibility,
+                });
+            }
+        }
+
+        (generics, edges)
+    }
+
+    /// Local types instantiated inside `func`: struct literals, tuple struct
+    /// calls, unit structs and enum variants; `Self` means `owner`.
+    fn constructs(&self, func: Node, owner: Option<&str>) -> BTreeSet<String> {
+        let mut paths = Vec::new();
+        if let Some(body) = func.child_by_field_name("body") {
+            constructed_paths(body, self.src, &mut paths);
+        }
+
+        let resolve = |path: &str| match (path, owner) {
+            ("Self", Some(owner)) => Some(owner.to_string()),
+            _ => self.resolver.resolve_type(&self.module, path),
+        };
+        paths
+            .iter()
+            .filter_map(|path| {
+                resolve(path).or_else(|| {
+                    // `Enum::Variant`, told apart from `Type::function` by case.
+                    let (parent, last) = path.rsplit_once("::")?;
+                    if !last.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        return None;
                     }
+                    resolve(parent)
+                })
+            })
+            .collect()
+    }
+
+    /// Note a reference from the current module to an item of another local module.
+    fn record_module_ref(&mut self, path: &str) {
+        let path = path.strip_suffix("::*").unwrap_or(path);
+        let Some((target, item)) = self.resolver.locate(&self.module, path) else {
+            return;
+        };
+        if target == self.module {
+            return;
+        }
+        self.module_deps
+            .entry(self.module.clone())
+            .or_default()
+            .entry(target)
+            .or_default()
+            .insert(item);
+    }
+
+    /// Record qualified paths inside a node that `extract_items` does not visit.
+    fn record_path_refs(&mut self, node: Node) {
+        match node.kind() {
+            "scoped_identifier" | "scoped_type_identifier" => {
+                self.record_module_ref(node.utf8_text(self.src.as_bytes()).unwrap());
+            }
+            _ => {
+                let mut cursor = node.walk();
+                for child in node.children(&mut cursor) {
+                    self.record_path_refs(child);
                 }
             }
+        }
+    }
 
+    /// Class for `path`, created empty if an impl block is seen before its type.
+    fn class_entry(&mut self, path: &str) -> &mut Class {
+        self.classes
+            .entry(path.to_string())
+            .or_insert_with(|| Class {
+                name: short_name(path).to_string(),
+                path: path.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Full path of a local trait, or the bare name of an external one.
+    fn trait_ref(&self, node: Node) -> Option<String> {
+        let path = base_type_path(node, self.src)?;
+        Some(self.trait_path(&path))
+    }
+
+    /// Like [`Self::trait_ref`], for a path given as text.
+    fn trait_path(&self, path: &str) -> String {
+        self.resolver
+            .resolve_type(&self.module, path)
+            .unwrap_or_else(|| short_name(path).to_string())
+    }
+}
+
+/// Add a declared type to `classes`. An impl block seen earlier, possibly in
+/// another file, only left a placeholder with its methods and trait impls;
+/// these are kept and everything else is taken from `declared`.
+///
+/// `None` when the type is already declared, e.g. by another `#[cfg]`
+/// variant; the first declaration keeps its members.
+fn declare(classes: &mut HashMap<String, Class>, declared: Class) -> Option<&mut Class> {
+    let class = classes.entry(declared.path.clone()).or_default();
+    // Declarations always carry a stereotype, placeholders never do.
+    if class.stereotype.is_some() {
+        return None;
+    }
+    let placeholder = mem::replace(class, declared);
+    class.methods = placeholder.methods;
+    class.trait_impls = placeholder.trait_impls;
+    class.impl_modules = placeholder.impl_modules;
+    Some(class)
+}
+
+/// A `const` / `static` item; `None` without a name or type.
+fn constant(node: Node, src: &str) -> Option<Constant> {
+    let name = node
+        .child_by_field_name("name")?
+        .utf8_text(src.as_bytes())
+        .ok()?;
+    let ty = node.child_by_field_name("type")?;
+
+    let mut cursor = node.walk();
+    let mutable = node
+        .children(&mut cursor)
+        .any(|c| c.kind() == "mutable_specifier");
+
+    Some(Constant {
+        name: name.to_string(),
+        ty: compact_text(ty, src),
+        visibility: visibility_of(node, src),
+        is_static: node.kind() == "static_item",
+        mutable,
+        inline_module: String::new(),
+        exported: false,
+    })
+}
+
+/// `parent` is the enclosing module for free functions, or the type path for methods.
+fn function_info(func: Node, src: &str, module: &str, parent: &str) -> Option<FunctionInfo> {
+    let name = func
+        .child_by_field_name("name")?
+        .utf8_text(src.as_bytes())
+        .ok()?
+        .to_string();
+
+    Some(FunctionInfo {
+        path: format!("{parent}::{name}"),
+        module: module.to_string(),
+        inline_module: String::new(),
+        name,
+        visibility: visibility_of(func, src),
+        exported: false,
+        doc: leading_doc_comment(func, src),
+        params: extract_function_params(func, src),
+        ret: extract_function_return_type(func, src),
+        is_abstract: func.kind() == "function_signature_item",
+        body: func
+            .child_by_field_name("body")
+            .map(|body| extract_steps(body, src, &local_types(func, src)))
+            .unwrap_or_default(),
+        constructs: BTreeSet::new(),
+        uses: BTreeSet::new(),
+        test: None,
+        trait_impl: None,
+    })
+}
+
+/// Test details when `attrs` carry one of `test_attributes`.
+fn test_info(attrs: &[Attribute], test_attributes: &[&str]) -> Option<TestInfo> {
+    let path = |attr: &Attribute| attr.path.trim_start_matches("::").to_string();
+    let marker = attrs
+        .iter()
+        .map(path)
+        .find(|p| test_attributes.contains(&p.as_str()))?;
+
+    let mut info = TestInfo {
+        marker,
+        ..Default::default()
+    };
+    for attr in attrs {
+        match path(attr).as_str() {
+            "ignore" => info.ignored = true,
+            "should_panic" => info.should_panic = true,
+            "test_case" | "case" => info.cases += 1,
+            p if p.starts_with("case::") => info.cases += 1,
             _ => {}
         }
+    }
+    Some(info)
+}
 
-        extract_items(
-            child,
-            src,
-            file_name,
-            file_module,
-            local_types,
-            classes,
-            file_functions_main,
-            file_functions_tests,
-        );
+/// Test functions declared inside a test macro such as `proptest! { .. }`,
+/// when `name!` is listed in `test_attributes`.
+fn macro_tests(
+    invocation: Node,
+    src: &str,
+    module: &str,
+    test_attributes: &[&str],
+) -> Vec<FunctionInfo> {
+    let bytes = src.as_bytes();
+    let Some(name) = invocation
+        .child_by_field_name("macro")
+        .and_then(|m| m.utf8_text(bytes).ok())
+    else {
+        return Vec::new();
+    };
+    let marker = format!("{name}!");
+    if !test_attributes.contains(&marker.as_str()) {
+        return Vec::new();
+    }
+
+    // The body is an unparsed token tree: pick up `fn name(params)`.
+    let mut tests = Vec::new();
+    let mut cursor = invocation.walk();
+    for tokens in invocation.named_children(&mut cursor) {
+        let children: Vec<Node> = {
+            let mut c = tokens.walk();
+            tokens.children(&mut c).collect()
+        };
+        for (i, token) in children.iter().enumerate() {
+            if token.kind() != "fn" {
+                continue;
+            }
+            let Some(name) = children.get(i + 1).and_then(|n| n.utf8_text(bytes).ok()) else {
+                continue;
+            };
+            let params = children
+                .get(i + 2)
+                .filter(|n| n.kind() == "token_tree")
+                .and_then(|n| n.utf8_text(bytes).ok())
+                .map(|p| p.trim_start_matches('(').trim_end_matches(')').trim())
+                .filter(|p| !p.is_empty())
+                .map(|p| vec![p.to_string()])
+                .unwrap_or_default();
+            tests.push(FunctionInfo {
+                name: name.to_string(),
+                path: format!("{module}::{name}"),
+                module: module.to_string(),
+                inline_module: String::new(),
+                visibility: Visibility::Private,
+                exported: false,
+                doc: None,
+                params,
+                ret: None,
+                is_abstract: false,
+                body: Vec::new(),
+                constructs: BTreeSet::new(),
+                uses: BTreeSet::new(),
+                test: Some(TestInfo {
+                    marker: marker.clone(),
+                    ..Default::default()
+                }),
+                trait_impl: None,
+            });
+        }
+    }
+    tests
+}
+
+/// Calls inside a function body and the control flow around them,
+/// skipping nested items.
+fn extract_steps(node: Node, src: &str, locals: &HashMap<String, String>) -> Vec<Step> {
+    let field_steps = |field: &str| {
+        node.child_by_field_name(field)
+            .map(|n| extract_steps(n, src, locals))
+            .unwrap_or_default()
+    };
+    let field_text = |field: &str| {
+        node.child_by_field_name(field)
+            .map(|n| compact_text(n, src))
+            .unwrap_or_default()
+    };
+
+    match node.kind() {
+        "function_item" | "impl_item" | "trait_item" | "mod_item" => Vec::new(),
+        "macro_invocation" => {
+            macro_arguments(node, src, |body, src| children_steps(body, src, locals))
+                .unwrap_or_else(|| children_steps(node, src, locals))
+        }
+        "call_expression" => {
+            // Arguments (and receivers) are evaluated before the call itself.
+            let mut steps = children_steps(node, src, locals);
+            let function = node.child_by_field_name("function");
+            if let Some(callee) = function.and_then(|f| callee_of(f, src)) {
+                // `value.method()` on a binding whose type is declared.
+                let receiver = function
+                    .and_then(|f| f.child_by_field_name("value"))
+                    .filter(|v| v.kind() == "identifier")
+                    .and_then(|v| v.utf8_text(src.as_bytes()).ok())
+                    .and_then(|v| locals.get(v))
+                    .cloned();
+                steps.push(Step::Call(Call {
+                    callee,
+                    receiver,
+                    target: None,
+                }));
+            }
+            steps
+        }
+        "if_expression" => {
+            let mut steps = field_steps("condition");
+            let mut branches = vec![(field_text("condition"), field_steps("consequence"))];
+
+            let mut alternative = node.child_by_field_name("alternative");
+            while let Some(else_clause) = alternative.and_then(|e| e.named_child(0)) {
+                alternative = None;
+                if else_clause.kind() == "if_expression" {
+                    let condition = else_clause.child_by_field_name("condition");
+                    let mut branch: Vec<Step> = condition
+                        .map(|c| extract_steps(c, src, locals))
+                        .unwrap_or_default();
+                    if let Some(consequence) = else_clause.child_by_field_name("consequence") {
+                        branch.extend(extract_steps(consequence, src, locals));
+                    }
+                    let label = condition.map(|c| compact_text(c, src)).unwrap_or_default();
+                    branches.push((label, branch));
+                    alternative = else_clause.child_by_field_name("alternative");
+                } else {
+                    branches.push((String::new(), extract_steps(else_clause, src, locals)));
+                }
+            }
+
+            if branches.iter().any(|(_, b)| !b.is_empty()) {
+                steps.push(Step::Branch(branches));
+            }
+            steps
+        }
+        "match_expression" => {
+            let mut steps = field_steps("value");
+            let mut branches = Vec::new();
+            if let Some(block) = node.child_by_field_name("body") {
+                let mut cursor = block.walk();
+                for arm in block.named_children(&mut cursor) {
+                    if arm.kind() != "match_arm" {
+                        continue;
+                    }
+                    let label = arm
+                        .child_by_field_name("pattern")
+                        .map(|p| compact_text(p, src))
+                        .unwrap_or_default();
+                    let arm_steps = arm
+                        .child_by_field_name("value")
+                        .map(|v| extract_steps(v, src, locals))
+                        .unwrap_or_default();
+                    branches.push((label, arm_steps));
+                }
+            }
+            if branches.iter().any(|(_, b)| !b.is_empty()) {
+                steps.push(Step::Branch(branches));
+            }
+            steps
+        }
+        "for_expression" => {
+            let mut steps = field_steps("value");
+            let label = format!("for {} in {}", field_text("pattern"), field_text("value"));
+            let body = field_steps("body");
+            if !body.is_empty() {
+                steps.push(Step::Loop(label, body));
+            }
+            steps
+        }
+        "while_expression" => {
+            let mut body = field_steps("condition");
+            body.extend(field_steps("body"));
+            if body.is_empty() {
+                return body;
+            }
+            vec![Step::Loop(
+                format!("while {}", field_text("condition")),
+                body,
+            )]
+        }
+        "loop_expression" => {
+            let body = field_steps("body");
+            if body.is_empty() {
+                return body;
+            }
+            vec![Step::Loop("loop".into(), body)]
+        }
+        _ => children_steps(node, src, locals),
     }
 }
 
-fn extract_type_identifiers(node: Node, src: &str) -> Vec<String> {
-    let mut result = vec![];
+/// Macros of the standard library whose arguments are expressions.
+const EXPRESSION_MACROS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "dbg",
+    "eprint",
+    "eprintln",
+    "format",
+    "format_args",
+    "panic",
+    "print",
+    "println",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "vec",
+    "write",
+    "writeln",
+];
+
+/// Run `f` on the arguments of an [`EXPRESSION_MACROS`] invocation, which
+/// tree-sitter leaves as a bare token tree, re-parsed as a block with one
+/// expression statement (`assert_eq!(a, b)` -> `{ (a, b); }`). `None` for
+/// any other macro.
+fn macro_arguments<T>(invocation: Node, src: &str, f: impl FnOnce(Node, &str) -> T) -> Option<T> {
+    let name = invocation
+        .child_by_field_name("macro")?
+        .utf8_text(src.as_bytes())
+        .ok()?;
+    let name = short_name(name);
+    if !EXPRESSION_MACROS.contains(&name) {
+        return None;
+    }
+    let mut cursor = invocation.walk();
+    let tokens = invocation
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "token_tree")?
+        .utf8_text(src.as_bytes())
+        .ok()?;
+    let inner = tokens.get(1..tokens.len().checked_sub(1)?)?;
+    // `vec![x; n]` is an array expression, everything else a tuple.
+    let source = match name {
+        "vec" => format!("fn m() {{ [{inner}]; }}"),
+        _ => format!("fn m() {{ ({inner}); }}"),
+    };
+
+    let mut parser = Parser::new();
+    parser.set_language(&tree_sitter_rust::language()).ok()?;
+    let tree = parser.parse(&source, None)?;
+    let body = tree
+        .root_node()
+        .named_child(0)?
+        .child_by_field_name("body")?;
+    Some(f(body, &source))
+}
+
+fn children_steps(node: Node, src: &str, locals: &HashMap<String, String>) -> Vec<Step> {
     let mut cursor = node.walk();
-    for child in node.children(&mut cursor) {
-        if child.kind() == "type_identifier" {
-            result.push(child.utf8_text(src.as_bytes()).unwrap().to_string());
+    node.children(&mut cursor)
+        .flat_map(|child| extract_steps(child, src, locals))
+        .collect()
+}
+
+/// Types of the parameters and `let` bindings of `func` by name, as written
+/// or as implied by the value (`Type::new()`, `Type { .. }`, `Type(..)`).
+/// Shadowing is ignored.
+fn local_types(func: Node, src: &str) -> HashMap<String, String> {
+    fn binding_name(pattern: Node, src: &str) -> Option<String> {
+        let text = pattern.utf8_text(src.as_bytes()).ok()?;
+        let name = text.trim_start_matches("mut ").trim();
+        name.chars()
+            .all(|c| c.is_alphanumeric() || c == '_')
+            .then(|| name.to_string())
+    }
+
+    fn declared(ty: Node, src: &str) -> Option<String> {
+        match ty.kind() {
+            "reference_type" => declared(ty.child_by_field_name("type")?, src),
+            _ => base_type_path(ty, src),
+        }
+    }
+
+    fn implied(value: Node, src: &str) -> Option<String> {
+        match value.kind() {
+            "struct_expression" => base_type_path(value.child_by_field_name("name")?, src),
+            "try_expression" | "reference_expression" => implied(value.named_child(0)?, src),
+            "call_expression" => {
+                let function = value.child_by_field_name("function")?;
+                let path = function.utf8_text(src.as_bytes()).ok()?;
+                match function.kind() {
+                    // `Foo(..)`
+                    "identifier" => Some(path.to_string()),
+                    // `Foo::new(..)`
+                    "scoped_identifier" => path.rsplit_once("::").map(|(ty, _)| ty.to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn lets(node: Node, src: &str, out: &mut HashMap<String, String>) {
+        if node.kind() == "let_declaration"
+            && let Some(name) = node
+                .child_by_field_name("pattern")
+                .and_then(|p| binding_name(p, src))
+            && let Some(ty) = node
+                .child_by_field_name("type")
+                .and_then(|t| declared(t, src))
+                .or_else(|| implied(node.child_by_field_name("value")?, src))
+        {
+            out.insert(name, ty);
+        }
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if !matches!(child.kind(), "function_item" | "impl_item" | "mod_item") {
+                lets(child, src, out);
+            }
+        }
+    }
+
+    let mut out = HashMap::new();
+    if let Some(params) = func.child_by_field_name("parameters") {
+        let mut cursor = params.walk();
+        for param in params.named_children(&mut cursor) {
+            if let (Some(name), Some(ty)) = (
+                param
+                    .child_by_field_name("pattern")
+                    .and_then(|p| binding_name(p, src)),
+                param
+                    .child_by_field_name("type")
+                    .and_then(|t| declared(t, src)),
+            ) {
+                out.insert(name, ty);
+            }
+        }
+    }
+    if let Some(body) = func.child_by_field_name("body") {
+        lets(body, src, &mut out);
+    }
+    out
+}
+
+fn callee_of(function: Node, src: &str) -> Option<Callee> {
+    let text = |n: Node| n.utf8_text(src.as_bytes()).ok().map(str::to_string);
+    match function.kind() {
+        "identifier" | "scoped_identifier" => Some(Callee::Path(text(function)?)),
+        // `foo::<T>()`
+        "generic_function" => callee_of(function.child_by_field_name("function")?, src),
+        "field_expression" => {
+            let method = text(function.child_by_field_name("field")?)?;
+            match function.child_by_field_name("value")?.kind() {
+                "self" => Some(Callee::SelfMethod(method)),
+                _ => Some(Callee::Method(method)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Resolve a call made inside `module` (and the impl of `owner`, if any) to a
+/// project function in `known`.
+fn resolve_call(
+    resolver: &Resolver,
+    known: &HashSet<String>,
+    module: &str,
+    owner: Option<&str>,
+    call: &Call,
+) -> Option<String> {
+    let found = |path: String| known.contains(&path).then_some(path);
+    match &call.callee {
+        Callee::SelfMethod(method) => found(format!("{}::{}", owner?, method)),
+        // Only through the receiver's type: a method name alone says nothing
+        // about which type (possibly a foreign one) the call goes to.
+        Callee::Method(method) => {
+            let ty = match (call.receiver.as_deref()?, owner) {
+                ("Self", Some(owner)) => owner.to_string(),
+                (ty, _) => resolver.resolve_type(module, ty)?,
+            };
+            found(format!("{ty}::{method}"))
+        }
+        Callee::Path(path) => {
+            if let (Some(rest), Some(owner)) = (path.strip_prefix("Self::"), owner) {
+                return found(format!("{owner}::{rest}"));
+            }
+            if let Some(target) = resolver.resolve(module, path) {
+                return found(target);
+            }
+            // `Type::associated_fn()`
+            let (ty, name) = path.rsplit_once("::")?;
+            let ty = resolver.resolve_type(module, ty)?;
+            found(format!("{ty}::{name}"))
+        }
+    }
+}
+
+/// Source text of a node with whitespace collapsed to single spaces.
+fn compact_text(node: Node, src: &str) -> String {
+    node.utf8_text(src.as_bytes())
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Visibility declared by the `visibility_modifier` child of `node`, if any.
+fn visibility_of(node: Node, src: &str) -> Visibility {
+    let mut cursor = node.walk();
+    let Some(modifier) = node
+        .children(&mut cursor)
+        .find(|c| c.kind() == "visibility_modifier")
+    else {
+        return Visibility::Private;
+    };
+    modifier_visibility(modifier, src)
+}
+
+/// Visibility spelled by a `visibility_modifier` node.
+fn modifier_visibility(modifier: Node, src: &str) -> Visibility {
+    let text: String = modifier
+        .utf8_text(src.as_bytes())
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+    match text.as_str() {
+        "pub" => Visibility::Public,
+        "pub(crate)" | "crate" => Visibility::Crate,
+        "pub(self)" => Visibility::Private,
+        _ => Visibility::Restricted,
+    }
+}
+
+/// Path of a type node without its generics (`a::Foo<T>` -> `a::Foo`).
+fn base_type_path(ty: Node, src: &str) -> Option<String> {
+    match ty.kind() {
+        "type_identifier" | "scoped_type_identifier" => {
+            ty.utf8_text(src.as_bytes()).ok().map(str::to_string)
+        }
+        "generic_type" => base_type_path(ty.child_by_field_name("type")?, src),
+        _ => None,
+    }
+}
+
+/// Named fields of a `{ .. }` body, or positional fields of a `( .. )` body
+/// named `0`, `1`, ...: (name, type node, visibility).
+fn struct_fields<'t>(body: Node<'t>, src: &str) -> Vec<(String, Node<'t>, Visibility)> {
+    let mut fields = Vec::new();
+    let mut cursor = body.walk();
+
+    if body.kind() == "ordered_field_declaration_list" {
+        // Modifiers and types are siblings: `(pub Inner, u8)`.
+        let mut visibility = Visibility::Private;
+        for child in body.named_children(&mut cursor) {
+            match child.kind() {
+                "visibility_modifier" => visibility = modifier_visibility(child, src),
+                "attribute_item" | "line_comment" | "block_comment" => {}
+                _ => {
+                    fields.push((fields.len().to_string(), child, visibility));
+                    visibility = Visibility::Private;
+                }
+            }
+        }
+        return fields;
+    }
+
+    for field in body.named_children(&mut cursor) {
+        if field.kind() != "field_declaration" {
+            continue;
+        }
+        let name = field
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(src.as_bytes()).ok());
+        if let (Some(name), Some(ty)) = (name, field.child_by_field_name("type")) {
+            fields.push((name.to_string(), ty, visibility_of(field, src)));
+        }
+    }
+    fields
+}
+
+/// Local types named in the parameters and return type of `func` inside
+/// `module`, ignoring its own and `outer` type parameters.
+fn signature_types(
+    func: Node,
+    outer: &[String],
+    src: &str,
+    module: &str,
+    resolver: &Resolver,
+) -> BTreeSet<String> {
+    let mut generics = type_parameter_names(func, src);
+    generics.extend_from_slice(outer);
+
+    let mut types = Vec::new();
+    if let Some(params) = func.child_by_field_name("parameters") {
+        let mut c = params.walk();
+        for param in params.named_children(&mut c) {
+            if let Some(ty) = param.child_by_field_name("type") {
+                types.extend(type_links(ty, src).into_iter().map(|l| l.path));
+            }
+        }
+    }
+    if let Some(ret) = func.child_by_field_name("return_type") {
+        types.extend(type_links(ret, src).into_iter().map(|l| l.path));
+    }
+
+    types
+        .iter()
+        .filter(|ty| !is_type_parameter(&generics, ty))
+        .filter_map(|ty| resolver.resolve_type(module, ty))
+        .collect()
+}
+
+/// Names of the type and const parameters declared on `node` (lifetimes
+/// excluded).
+fn type_parameter_names(node: Node, src: &str) -> Vec<String> {
+    declared_type_parameters(node)
+        .into_iter()
+        .filter_map(|(name, _)| name.utf8_text(src.as_bytes()).ok())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Name and inline bounds (`T: Clone`) of every type and const parameter
+/// declared on `node`.
+fn declared_type_parameters(node: Node) -> Vec<(Node, Option<Node>)> {
+    fn parameter(param: Node) -> Option<(Node, Option<Node>)> {
+        match param.kind() {
+            "type_identifier" => Some((param, None)),
+            "constrained_type_parameter" => Some((
+                param.child_by_field_name("left")?,
+                param.child_by_field_name("bounds"),
+            )),
+            // `T = u8`, `T: Clone = u8`
+            "optional_type_parameter" => parameter(param.child_by_field_name("name")?),
+            "const_parameter" => Some((param.child_by_field_name("name")?, None)),
+            _ => None,
+        }
+    }
+
+    let Some(params) = node.child_by_field_name("type_parameters") else {
+        return Vec::new();
+    };
+    let mut cursor = params.walk();
+    params
+        .named_children(&mut cursor)
+        .filter_map(parameter)
+        .collect()
+}
+
+/// Whether a type path as written starts with one of the item's own type
+/// parameters (`K`, `K::Item`).
+fn is_type_parameter(generics: &[String], ty: &str) -> bool {
+    let head = ty.split("::").next().unwrap_or(ty);
+    generics.iter().any(|g| g == head)
+}
+
+/// Paths that may build a value under `node`, as written: struct literal
+/// names, called paths and plain paths in expressions. Patterns and nested
+/// items are skipped.
+fn constructed_paths(node: Node, src: &str, out: &mut Vec<String>) {
+    match node.kind() {
+        "function_item" | "impl_item" | "trait_item" | "mod_item" => return,
+        "macro_invocation" => {
+            // Other macros only have their tokens to go by.
+            let parsed = macro_arguments(node, src, |body, src| constructed_paths(body, src, out));
+            if parsed.is_some() {
+                return;
+            }
+        }
+        "match_pattern"
+        | "tuple_struct_pattern"
+        | "struct_pattern"
+        | "or_pattern"
+        | "tuple_pattern"
+        | "slice_pattern"
+        | "ref_pattern"
+        | "captured_pattern" => return,
+        "struct_expression" => {
+            if let Some(path) = node
+                .child_by_field_name("name")
+                .and_then(|n| base_type_path(n, src))
+            {
+                out.push(path);
+            }
+        }
+        "identifier" | "scoped_identifier" => {
+            if let Ok(path) = node.utf8_text(src.as_bytes()) {
+                out.push(path.to_string());
+            }
+            return;
+        }
+        "let_declaration" => {
+            // The bound pattern names no type.
+            if let Some(value) = node.child_by_field_name("value") {
+                constructed_paths(value, src, out);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        constructed_paths(child, src, out);
+    }
+}
+
+/// A named type inside a field type, and how the field holds it.
+struct TypeLink {
+    /// Type path as written ("Foo", "a::Foo").
+    path: String,
+    /// `*--` owned, `o--` shared or borrowed, `-->` weak.
+    edge: &'static str,
+    multiplicity: Option<&'static str>,
+    /// Key type when held as a map value.
+    key: Option<String>,
+    /// Named as `dyn Trait` / `impl Trait`.
+    trait_object: bool,
+}
+
+impl TypeLink {
+    /// Short type name for edge labels (`Storage`, `dyn Storage`).
+    fn display(&self) -> String {
+        let name = short_name(&self.path);
+        if self.trait_object {
+            format!("dyn {name}")
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+/// Every named type referenced by a field type with its edge, multiplicity and
+/// map key: `Option<T>` is `0..1`, collections, arrays and slices `*`,
+/// `&T`/`Rc<T>`/`Arc<T>` aggregation, `Weak<T>` and `dyn Trait` association,
+/// and `Box<T>` or a plain `T` composition.
+fn type_links(node: Node, src: &str) -> Vec<TypeLink> {
+    let mut links = Vec::new();
+    collect_type_links(node, src, "*--", None, None, &mut links);
+    links
+}
+
+fn collect_type_links(
+    node: Node,
+    src: &str,
+    edge: &'static str,
+    multiplicity: Option<&'static str>,
+    key: Option<&str>,
+    out: &mut Vec<TypeLink>,
+) {
+    // Weaker ownership wins over an enclosing stronger one (`Vec<Rc<T>>`).
+    let weaken = |to: &'static str| {
+        let rank = |e: &str| ["*--", "o--", "-->"].iter().position(|x| *x == e);
+        if rank(to) > rank(edge) { to } else { edge }
+    };
+    let many = Some("*");
+    let optional = multiplicity.or(Some("0..1"));
+    let mut recurse = |child: Node, edge, multiplicity, key: Option<&str>| {
+        collect_type_links(child, src, edge, multiplicity, key, out)
+    };
+
+    match node.kind() {
+        "type_identifier" | "scoped_type_identifier" => out.push(TypeLink {
+            path: node.utf8_text(src.as_bytes()).unwrap().to_string(),
+            edge,
+            multiplicity,
+            key: key.map(str::to_string),
+            trait_object: false,
+        }),
+        // `dyn Storage`, `impl Into<Config>`: the trait itself is only used
+        // through an interface; its generic arguments are ordinary types.
+        "dynamic_type" | "abstract_type" => {
+            let Some(bound) = node.child_by_field_name("trait") else {
+                return;
+            };
+            let (base, args) = match bound.kind() {
+                "generic_type" => (
+                    bound.child_by_field_name("type"),
+                    bound.child_by_field_name("type_arguments"),
+                ),
+                _ => (Some(bound), None),
+            };
+            match base.filter(|b| matches!(b.kind(), "type_identifier" | "scoped_type_identifier"))
+            {
+                Some(base) => out.push(TypeLink {
+                    path: base.utf8_text(src.as_bytes()).unwrap().to_string(),
+                    edge: weaken("-->"),
+                    multiplicity,
+                    key: key.map(str::to_string),
+                    trait_object: true,
+                }),
+                None => collect_type_links(bound, src, edge, multiplicity, key, out),
+            }
+            if let Some(args) = args {
+                collect_type_links(args, src, edge, multiplicity, key, out);
+            }
+        }
+        "reference_type" => {
+            if let Some(inner) = node.child_by_field_name("type") {
+                recurse(inner, weaken("o--"), multiplicity, key);
+            }
+        }
+        "array_type" => {
+            if let Some(element) = node.child_by_field_name("element") {
+                recurse(element, edge, many, key);
+            }
+        }
+        "generic_type" => {
+            let Some(base) = node.child_by_field_name("type") else {
+                return;
+            };
+            let args: Vec<Node> = node
+                .child_by_field_name("type_arguments")
+                .map(|a| {
+                    let mut c = a.walk();
+                    a.named_children(&mut c).collect()
+                })
+                .unwrap_or_default();
+
+            let base_text = base.utf8_text(src.as_bytes()).unwrap_or("");
+            match short_name(base_text) {
+                "Option" => args
+                    .into_iter()
+                    .for_each(|a| recurse(a, edge, optional, key)),
+                "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap" | "HashSet" | "BTreeSet"
+                | "IndexSet" => args.into_iter().for_each(|a| recurse(a, edge, many, key)),
+                "HashMap" | "BTreeMap" | "IndexMap" if args.len() == 2 => {
+                    let key_text = compact_text(args[0], src);
+                    recurse(args[0], edge, many, None);
+                    recurse(args[1], edge, many, Some(&key_text));
+                }
+                "Rc" | "Arc" => args
+                    .into_iter()
+                    .for_each(|a| recurse(a, weaken("o--"), multiplicity, key)),
+                "Weak" => args
+                    .into_iter()
+                    .for_each(|a| recurse(a, weaken("-->"), multiplicity, key)),
+                "Box" => args
+                    .into_iter()
+                    .for_each(|a| recurse(a, edge, multiplicity, key)),
+                // A local generic type is itself a target, as are its arguments.
+                _ => {
+                    recurse(base, edge, multiplicity, key);
+                    args.into_iter()
+                        .for_each(|a| recurse(a, edge, multiplicity, key));
+                }
+            }
+        }
+        _ => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                recurse(child, edge, multiplicity, key);
+            }
         }
-        result.extend(extract_type_identifiers(child, src));
     }
-    result
 }
 
 /// Extract a function's parameters as "name: Type" or "Type" strings.
@@ -453,3 +2111,350 @@ fn leading_doc_comment(item: Node, src: &str) -> Option<String> {
 
     last_doc_line
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a crate made of `files` (path under `src/` -> content), written
+    /// to a fresh directory named after the test.
+    fn project(test: &str, files: &[(&str, &str)]) -> ParsedProject {
+        let dir = std::env::temp_dir().join(format!("rust-to-mermaid-parse-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        parse_project(&dir).unwrap()
+    }
+
+    /// The function or method at `path`.
+    fn function<'a>(project: &'a ParsedProject, path: &str) -> &'a FunctionInfo {
+        project
+            .file_functions_main
+            .values()
+            .chain(project.file_functions_tests.values())
+            .flatten()
+            .chain(project.classes.values().flat_map(|c| &c.methods))
+            .find(|f| f.path == path)
+            .unwrap_or_else(|| panic!("no function {path}"))
+    }
+
+    fn targets(function: &FunctionInfo) -> Vec<&str> {
+        function
+            .calls()
+            .into_iter()
+            .filter_map(|c| c.target.as_deref())
+            .collect()
+    }
+
+    fn test_info_of(src: &str) -> Option<TestInfo> {
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(src, None).unwrap();
+        let root = tree.root_node();
+        let func = root.named_children(&mut root.walk()).last().unwrap();
+        test_info(&outer_attributes(func, src), DEFAULT_TEST_ATTRIBUTES)
+    }
+
+    #[test]
+    fn recognises_test_attributes() {
+        assert!(test_info_of("#[inline] fn f() {}").is_none());
+        assert!(test_info_of("#[my::test] fn f() {}").is_none());
+
+        let info = test_info_of("#[::tokio::test] #[ignore] #[should_panic] fn f() {}").unwrap();
+        assert_eq!(info.marker, "tokio::test");
+        assert!(info.ignored);
+        assert!(info.should_panic);
+        assert_eq!(info.cases, 0);
+    }
+
+    #[test]
+    fn counts_parameterized_cases() {
+        let info = test_info_of("#[rstest] #[case(1)] #[case::two(2)] fn f(#[case] n: u8) {}");
+        assert_eq!(info.unwrap().cases, 2);
+
+        let info = test_info_of("#[test_case(1)] #[test_case(2)] #[test_case(3)] fn f(n: u8) {}");
+        let info = info.unwrap();
+        assert_eq!(info.marker, "test_case");
+        assert_eq!(info.cases, 3);
+    }
+
+    #[test]
+    fn resolves_paths_self_methods_and_associated_functions() {
+        let project = project(
+            "calls",
+            &[(
+                "lib.rs",
+                "mod util { pub fn helper() {} }
+                pub struct Shape;
+                impl Shape {
+                    pub fn new() -> Self { Self::make() }
+                    fn make() -> Self { Shape }
+                    pub fn area(&self) -> u8 { self.side(); util::helper(); 0 }
+                    fn side(&self) {}
+                }
+                pub fn build() { let s = Shape::new(); crate::util::helper(); other::thing(); }",
+            )],
+        );
+        assert_eq!(
+            targets(function(&project, "crate::Shape::new")),
+            ["crate::Shape::make"]
+        );
+        assert_eq!(
+            targets(function(&project, "crate::Shape::area")),
+            ["crate::Shape::side", "crate::util::helper"]
+        );
+        let build = function(&project, "crate::build");
+        assert_eq!(targets(build), ["crate::Shape::new", "crate::util::helper"]);
+        assert_eq!(build.calls().len(), 3);
+    }
+
+    #[test]
+    fn resolves_method_calls_only_through_known_receivers() {
+        let project = project(
+            "method-receivers",
+            &[(
+                "lib.rs",
+                "pub struct Bag { items: Vec<u8> }
+                impl Bag {
+                    pub fn len(&self) -> usize { self.items.len() }
+                    pub fn twice(&self) -> usize { self.len() + Self::len(self) }
+                }
+                pub fn count(v: Vec<u8>) -> usize { v.len() }
+                pub fn measure(bag: &Bag) -> usize { bag.len() }",
+            )],
+        );
+        assert!(targets(function(&project, "crate::Bag::len")).is_empty());
+        assert!(targets(function(&project, "crate::count")).is_empty());
+        assert_eq!(
+            targets(function(&project, "crate::Bag::twice")),
+            ["crate::Bag::len", "crate::Bag::len"]
+        );
+        assert_eq!(
+            targets(function(&project, "crate::measure")),
+            ["crate::Bag::len"]
+        );
+    }
+
+    #[test]
+    fn sees_calls_and_constructions_inside_std_macros() {
+        let project = project(
+            "macros",
+            &[(
+                "lib.rs",
+                "pub struct Cache;
+                pub struct Key(u8);
+                impl Cache {
+                    pub fn new() -> Self { Cache }
+                    pub fn run(&self, key: &Key) -> u8 { 1 }
+                }
+                #[test]
+                fn runs() {
+                    let cache = Cache::new();
+                    assert_eq!(cache.run(&Key(1)), 1, \"{}\", format!(\"{}\", 1));
+                    let caches = vec![Cache::new(); 2];
+                }",
+            )],
+        );
+        let runs = function(&project, "crate::runs");
+        assert_eq!(
+            targets(runs),
+            [
+                "crate::Cache::new",
+                "crate::Cache::run",
+                "crate::Cache::new"
+            ]
+        );
+        assert!(runs.constructs.contains("crate::Key"));
+    }
+
+    #[test]
+    fn keeps_function_local_items_out_of_the_module() {
+        let project = project(
+            "local-items",
+            &[(
+                "lib.rs",
+                "mod a { pub fn go() {} }
+                pub fn one() { struct Helper { a: u8 } fn inner() {} inner(); }
+                pub fn two() { struct Helper { b: String } use crate::a::go; go(); }",
+            )],
+        );
+        assert!(project.classes.is_empty());
+        let functions: Vec<&str> = project.file_functions_main["lib"]
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(functions, ["crate::a::go", "crate::one", "crate::two"]);
+        assert!(targets(function(&project, "crate::one")).is_empty());
+        assert_eq!(targets(function(&project, "crate::two")), ["crate::a::go"]);
+    }
+
+    /// (path, edge, multiplicity, key) of every link in the field type `ty`.
+    fn links_of(ty: &str) -> Vec<(String, &'static str, Option<&'static str>, Option<String>)> {
+        let src = format!("struct S {{ f: {ty} }}");
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(&src, None).unwrap();
+        let body = tree.root_node().named_child(0).unwrap();
+        let body = body.child_by_field_name("body").unwrap();
+        let (_, ty, _) = struct_fields(body, &src).remove(0);
+        type_links(ty, &src)
+            .into_iter()
+            .map(|l| (l.path, l.edge, l.multiplicity, l.key))
+            .collect()
+    }
+
+    fn link(
+        path: &str,
+        edge: &'static str,
+        multiplicity: Option<&'static str>,
+    ) -> (String, &'static str, Option<&'static str>, Option<String>) {
+        (path.to_string(), edge, multiplicity, None)
+    }
+
+    #[test]
+    fn derives_edges_and_multiplicities_from_wrappers() {
+        assert_eq!(links_of("Node"), [link("Node", "*--", None)]);
+        assert_eq!(links_of("a::Node"), [link("a::Node", "*--", None)]);
+        assert_eq!(links_of("Box<Node>"), [link("Node", "*--", None)]);
+        assert_eq!(
+            links_of("Option<Node>"),
+            [link("Node", "*--", Some("0..1"))]
+        );
+        assert_eq!(links_of("Vec<Node>"), [link("Node", "*--", Some("*"))]);
+        assert_eq!(links_of("HashSet<Node>"), [link("Node", "*--", Some("*"))]);
+        assert_eq!(links_of("[Node; 4]"), [link("Node", "*--", Some("*"))]);
+        assert_eq!(links_of("&'a Node"), [link("Node", "o--", None)]);
+        assert_eq!(links_of("Rc<Node>"), [link("Node", "o--", None)]);
+        assert_eq!(links_of("Arc<Node>"), [link("Node", "o--", None)]);
+        assert_eq!(links_of("Weak<Node>"), [link("Node", "-->", None)]);
+    }
+
+    #[test]
+    fn combines_nested_wrappers() {
+        assert_eq!(links_of("Vec<Rc<Node>>"), [link("Node", "o--", Some("*"))]);
+        assert_eq!(links_of("Rc<Vec<Node>>"), [link("Node", "o--", Some("*"))]);
+        assert_eq!(
+            links_of("Option<Vec<Node>>"),
+            [link("Node", "*--", Some("*"))]
+        );
+        assert_eq!(
+            links_of("Vec<Option<Node>>"),
+            [link("Node", "*--", Some("*"))]
+        );
+        assert_eq!(
+            links_of("Option<Weak<Node>>"),
+            [link("Node", "-->", Some("0..1"))]
+        );
+        // A weak link stays weak inside a shared one, not the other way round.
+        assert_eq!(links_of("Weak<Rc<Node>>"), [link("Node", "-->", None)]);
+        assert_eq!(
+            links_of("Tree<Node>"),
+            [link("Tree", "*--", None), link("Node", "*--", None)]
+        );
+    }
+
+    #[test]
+    fn labels_map_values_with_their_key() {
+        assert_eq!(
+            links_of("HashMap<Id, Node>"),
+            [
+                link("Id", "*--", Some("*")),
+                ("Node".to_string(), "*--", Some("*"), Some("Id".to_string())),
+            ]
+        );
+        // Non-local types such as `String` are dropped later by the resolver.
+        assert_eq!(
+            links_of("BTreeMap<String, Rc<Node>>"),
+            [
+                link("String", "*--", Some("*")),
+                (
+                    "Node".to_string(),
+                    "o--",
+                    Some("*"),
+                    Some("String".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_impls_seen_before_their_type() {
+        let project = project(
+            "impl-first",
+            &[
+                (
+                    "lib.rs",
+                    "mod model;
+                    impl model::Thing { pub fn go(&self) {} }
+                    impl std::fmt::Display for model::Thing {
+                        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { Ok(()) }
+                    }",
+                ),
+                ("model.rs", "/// A thing.\npub struct Thing { pub x: u8 }"),
+            ],
+        );
+        let thing = &project.classes["crate::model::Thing"];
+        assert_eq!(thing.stereotype.as_deref(), Some("struct"));
+        assert_eq!(thing.file, "model");
+        assert_eq!(thing.doc.as_deref(), Some("A thing."));
+        let fields: Vec<&str> = thing.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, ["x"]);
+        let methods: Vec<&str> = thing.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, ["go", "fmt"]);
+        assert!(thing.trait_impls.contains("Display"));
+        assert_eq!(thing.impl_modules, BTreeSet::from(["crate".to_string()]));
+    }
+
+    #[test]
+    fn keeps_the_first_of_several_declarations() {
+        let project = project(
+            "cfg-variants",
+            &[(
+                "lib.rs",
+                "pub struct Dep;
+                #[cfg(unix)]
+                pub struct Both { a: u8, dep: Dep }
+                #[cfg(not(unix))]
+                pub struct Both { a: u8, dep: Dep }
+                impl Both { pub fn go(&self) {} }
+                #[cfg(unix)]
+                pub enum Mode { Fast }
+                #[cfg(not(unix))]
+                pub enum Mode { Slow }",
+            )],
+        );
+        let both = &project.classes["crate::Both"];
+        let fields: Vec<&str> = both.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, ["a", "dep"]);
+        assert_eq!(both.relationships.len(), 1);
+        assert_eq!(both.methods.len(), 1);
+        let mode = &project.classes["crate::Mode"];
+        let variants: Vec<&str> = mode.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(variants, ["Fast"]);
+    }
+
+    #[test]
+    fn reads_type_parameters_and_their_bounds() {
+        let project = project(
+            "generics",
+            &[(
+                "lib.rs",
+                "pub trait Store {}
+                pub trait Codec {}
+                pub struct Cache<'a, K: Store = u8, const N: usize, V = i8>(&'a K, V)
+                where V: Codec;",
+            )],
+        );
+        let cache = &project.classes["crate::Cache"];
+        assert_eq!(cache.generics, ["K", "N", "V"]);
+        let labels: BTreeSet<&str> = cache
+            .relationships
+            .iter()
+            .filter_map(|r| r.label.as_deref())
+            .collect();
+        assert_eq!(labels, BTreeSet::from(["K: Store", "V: Codec"]));
+    }
+}
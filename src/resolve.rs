@@ -0,0 +1,503 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use tree_sitter::Node;
+
+/// Root segment of every module path when parsing a single crate; workspace
+/// members use their crate name instead.
+pub const CRATE_ROOT: &str = "crate";
+
+/// Guard against `use` cycles (`pub use a::*` in both `a` and `b`).
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Module,
+    Type,
+    Function,
+}
+
+/// Names brought into one module by `use` declarations.
+#[derive(Default)]
+struct Scope {
+    /// Local name -> path as written in the `use` (aliases included).
+    imports: HashMap<String, String>,
+    /// Paths glob-imported with `use path::*`, as written.
+    globs: Vec<String>,
+}
+
+/// Per-module name-resolution table built from item definitions, `mod`
+/// nesting and `use` declarations.
+#[derive(Default)]
+pub struct Resolver {
+    items: HashMap<String, ItemKind>,
+    scopes: HashMap<String, Scope>,
+    /// Crate root module -> other names for crates visible from it.
+    crate_roots: HashMap<String, BTreeMap<String, String>>,
+    /// Crate roots of binaries, whose items are never exported.
+    binary_roots: HashSet<String>,
+    /// Modules declared `pub mod`.
+    public_modules: HashSet<String>,
+    /// `pub use` declarations: (module, path as written); globs end in `::*`.
+    reexports: Vec<(String, String)>,
+}
+
+impl Resolver {
+    pub fn add_item(&mut self, path: String, kind: ItemKind) {
+        self.items.insert(path, kind);
+    }
+
+    /// Register `root` as the root module of a crate, where `crate::` paths
+    /// start and `aliases` (name -> crate root) name other crates.
+    pub fn add_crate_root(&mut self, root: String, aliases: BTreeMap<String, String>) {
+        self.crate_roots.insert(root, aliases);
+    }
+
+    /// Like [`Self::add_crate_root`], for a binary crate.
+    pub fn add_binary_root(&mut self, root: String, aliases: BTreeMap<String, String>) {
+        self.binary_roots.insert(root.clone());
+        self.add_crate_root(root, aliases);
+    }
+
+    /// Record every name imported by a `use_declaration` inside `module`.
+    pub fn add_use(&mut self, module: &str, use_decl: Node, src: &str) {
+        let Some(argument) = use_decl.child_by_field_name("argument") else {
+            return;
+        };
+        let scope = self.scopes.entry(module.to_string()).or_default();
+        collect_use_tree(argument, "", src, scope);
+    }
+
+    /// Mark `module` as declared with `pub mod`.
+    pub fn add_public_module(&mut self, module: String) {
+        self.public_modules.insert(module);
+    }
+
+    /// Record what a `pub use` declaration inside `module` re-exports.
+    pub fn add_reexport(&mut self, module: &str, use_decl: Node, src: &str) {
+        for path in use_paths(use_decl, src) {
+            self.reexports.push((module.to_string(), path));
+        }
+    }
+
+    /// Whether the item at `path` can be named from outside its crate, given
+    /// that it is `pub` itself: every enclosing module is `pub` up to the
+    /// crate root, or something on the way is re-exported by `pub use`.
+    pub fn is_exported(&self, path: &str) -> bool {
+        self.reexported(path, 0) || self.module_exported(parent_path(path), 0)
+    }
+
+    fn module_exported(&self, module: &str, depth: usize) -> bool {
+        if depth > MAX_DEPTH {
+            return false;
+        }
+        (self.crate_roots.contains_key(module) && !self.binary_roots.contains(module))
+            || self.reexported(module, depth)
+            || (self.public_modules.contains(module)
+                && self.module_exported(parent_path(module), depth + 1))
+    }
+
+    /// `path` is named by a `pub use` in an exported module.
+    fn reexported(&self, path: &str, depth: usize) -> bool {
+        self.reexports.iter().any(|(module, used)| {
+            let target = match used.strip_suffix("::*") {
+                Some(glob) => self
+                    .resolve(module, glob)
+                    .map(|m| format!("{m}::{}", short_name(path))),
+                None => self.resolve(module, used),
+            };
+            target.as_deref() == Some(path)
+                && module != path
+                && self.module_exported(module, depth + 1)
+        })
+    }
+
+    /// Full paths of every module in the project.
+    pub fn modules(&self) -> impl Iterator<Item = &String> {
+        self.items
+            .iter()
+            .filter(|(_, kind)| **kind == ItemKind::Module)
+            .map(|(path, _)| path)
+    }
+
+    /// The local module a path as written inside `module` points into, and the
+    /// referenced item (`crate::parse::Class` -> (`crate::parse`, `crate::parse::Class`)).
+    ///
+    /// Items the table does not know (functions, macros, ...) are attributed to
+    /// their parent module or type when that resolves.
+    pub fn locate(&self, module: &str, path: &str) -> Option<(String, String)> {
+        if let Some(full) = self.resolve(module, path) {
+            return Some(match self.items.get(&full) {
+                Some(ItemKind::Module) => (full.clone(), full),
+                _ => (parent_path(&full).to_string(), full),
+            });
+        }
+
+        let (parent, name) = path.rsplit_once("::")?;
+        let parent = self.resolve(module, parent)?;
+        Some(match self.items.get(&parent) {
+            Some(ItemKind::Module) => (parent.clone(), format!("{parent}::{name}")),
+            _ => (parent_path(&parent).to_string(), parent),
+        })
+    }
+
+    /// Resolve a type path as written inside `module` to a local type's full path.
+    pub fn resolve_type(&self, module: &str, path: &str) -> Option<String> {
+        self.resolve(module, path)
+            .filter(|p| self.items.get(p) == Some(&ItemKind::Type))
+    }
+
+    /// Resolve a path as written inside `module` to a local item's full path,
+    /// following imports, glob imports and re-exports.
+    pub fn resolve(&self, module: &str, path: &str) -> Option<String> {
+        self.resolve_at(module, path, 0)
+    }
+
+    fn resolve_at(&self, module: &str, path: &str, depth: usize) -> Option<String> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let (head, rest) = match path.split_once("::") {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (path, None),
+        };
+
+        let base = match head {
+            "crate" => match self.crate_root(module) {
+                Some(root) => root.to_string(),
+                None => absolute_path(module, head),
+            },
+            "self" | "super" => absolute_path(module, head),
+            _ => {
+                let own = format!("{module}::{head}");
+                if self.items.contains_key(&own) {
+                    own
+                } else if let Some(imported) = self.import(module, head) {
+                    self.resolve_at(module, imported, depth + 1)?
+                } else if let Some(target) = self
+                    .crate_root(module)
+                    .and_then(|root| self.crate_roots[root].get(head))
+                {
+                    target.clone()
+                } else if self.items.get(head) == Some(&ItemKind::Module) {
+                    // Another crate of the workspace, by its root module.
+                    head.to_string()
+                } else {
+                    self.glob_lookup(module, head, depth)?
+                }
+            }
+        };
+
+        let mut full = base;
+        for segment in rest.into_iter().flat_map(|r| r.split("::")) {
+            full = match segment {
+                "super" => absolute_path(&full, segment),
+                _ => self.canonical(&format!("{full}::{segment}"), depth)?,
+            };
+        }
+
+        self.canonical(&full, depth)
+    }
+
+    /// Follow re-exports until `path` names an item defined in the project.
+    fn canonical(&self, path: &str, depth: usize) -> Option<String> {
+        if self.items.contains_key(path) {
+            return Some(path.to_string());
+        }
+
+        let (parent, name) = path.rsplit_once("::")?;
+        if let Some(imported) = self.import(parent, name) {
+            return self.resolve_at(parent, imported, depth + 1);
+        }
+        self.glob_lookup(parent, name, depth)
+    }
+
+    /// The innermost registered crate root `module` lives in.
+    fn crate_root(&self, module: &str) -> Option<&str> {
+        self.crate_roots
+            .keys()
+            .filter(|root| {
+                module == root.as_str()
+                    || module
+                        .strip_prefix(root.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|root| root.len())
+            .map(String::as_str)
+    }
+
+    fn import(&self, module: &str, name: &str) -> Option<&str> {
+        self.scopes
+            .get(module)?
+            .imports
+            .get(name)
+            .map(String::as_str)
+    }
+
+    fn glob_lookup(&self, module: &str, name: &str, depth: usize) -> Option<String> {
+        let scope = self.scopes.get(module)?;
+        scope.globs.iter().find_map(|glob| {
+            let target = self.resolve_at(module, glob, depth + 1)?;
+            self.canonical(&format!("{target}::{name}"), depth + 1)
+        })
+    }
+}
+
+/// Every path a `use_declaration` imports, as written (globs end in `::*`).
+pub fn use_paths(use_decl: Node, src: &str) -> Vec<String> {
+    let mut scope = Scope::default();
+    if let Some(argument) = use_decl.child_by_field_name("argument") {
+        collect_use_tree(argument, "", src, &mut scope);
+    }
+
+    let mut paths: Vec<String> = scope.imports.into_values().collect();
+    paths.extend(scope.globs.into_iter().map(|g| format!("{g}::*")));
+    paths
+}
+
+/// Flatten a `use` tree into `scope`, prefixing every path with `prefix`.
+fn collect_use_tree(node: Node, prefix: &str, src: &str, scope: &mut Scope) {
+    let bytes = src.as_bytes();
+    let join = |path: &str| {
+        if prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{prefix}::{path}")
+        }
+    };
+
+    match node.kind() {
+        "identifier" | "scoped_identifier" | "crate" | "self" | "super" => {
+            let text = node.utf8_text(bytes).unwrap_or("");
+            // `use a::{self}` imports `a` itself.
+            let path = if text == "self" && !prefix.is_empty() {
+                prefix.to_string()
+            } else {
+                join(text)
+            };
+            let name = short_name(&path).to_string();
+            scope.imports.insert(name, path);
+        }
+        "use_as_clause" => {
+            let path = node
+                .child_by_field_name("path")
+                .and_then(|n| n.utf8_text(bytes).ok());
+            let alias = node
+                .child_by_field_name("alias")
+                .and_then(|n| n.utf8_text(bytes).ok());
+            if let (Some(path), Some(alias)) = (path, alias)
+                && alias != "_"
+            {
+                scope.imports.insert(alias.to_string(), join(path));
+            }
+        }
+        "scoped_use_list" => {
+            let prefix = match node
+                .child_by_field_name("path")
+                .and_then(|n| n.utf8_text(bytes).ok())
+            {
+                Some(path) => join(path),
+                None => prefix.to_string(),
+            };
+            if let Some(list) = node.child_by_field_name("list") {
+                collect_use_tree(list, &prefix, src, scope);
+            }
+        }
+        "use_list" => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                collect_use_tree(child, prefix, src, scope);
+            }
+        }
+        "use_wildcard" => {
+            let text = node.utf8_text(bytes).unwrap_or("");
+            let path = text.trim_end_matches('*').trim_end_matches("::");
+            scope.globs.push(if path.is_empty() {
+                prefix.to_string()
+            } else {
+                join(path)
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Last segment of a `::`-separated path.
+pub fn short_name(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Everything before the last `::` segment (`crate::a::B` -> `crate::a`).
+pub fn parent_path(path: &str) -> &str {
+    path.rsplit_once("::").map_or(path, |(parent, _)| parent)
+}
+
+/// Join `path` onto `module`, honouring leading `crate`/`self`/`super`.
+pub fn absolute_path(module: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = module.split("::").collect();
+    for (i, segment) in path.split("::").enumerate() {
+        match segment {
+            "crate" if i == 0 => segments.truncate(1),
+            "self" if i == 0 => {}
+            "super" => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tree_sitter::Parser;
+
+    /// A single crate with `items`, and the `use` declarations in `uses`
+    /// (module -> source) recorded like the first parsing pass does.
+    fn resolver(items: &[(&str, ItemKind)], uses: &[(&str, &str)]) -> Resolver {
+        let mut resolver = Resolver::default();
+        resolver.add_crate_root(CRATE_ROOT.to_string(), BTreeMap::new());
+        resolver.add_item(CRATE_ROOT.to_string(), ItemKind::Module);
+        for (path, kind) in items {
+            resolver.add_item(path.to_string(), *kind);
+        }
+
+        let mut parser = Parser::new();
+        parser.set_language(&tree_sitter_rust::language()).unwrap();
+        for (module, src) in uses {
+            let tree = parser.parse(src, None).unwrap();
+            let root = tree.root_node();
+            let mut cursor = root.walk();
+            for decl in root.named_children(&mut cursor) {
+                resolver.add_use(module, decl, src);
+                if decl.utf8_text(src.as_bytes()).unwrap().starts_with("pub ") {
+                    resolver.add_reexport(module, decl, src);
+                }
+            }
+        }
+        resolver
+    }
+
+    const ITEMS: &[(&str, ItemKind)] = &[
+        ("crate::a", ItemKind::Module),
+        ("crate::b", ItemKind::Module),
+        ("crate::b::inner", ItemKind::Module),
+        ("crate::b::Thing", ItemKind::Type),
+        ("crate::b::inner::Deep", ItemKind::Type),
+        ("crate::b::inner::Hidden", ItemKind::Type),
+        ("crate::b::helper", ItemKind::Function),
+    ];
+
+    #[test]
+    fn follows_imports_and_aliases() {
+        let r = resolver(
+            ITEMS,
+            &[(
+                "crate::a",
+                "use crate::b::Thing as Other; use crate::b::{self, helper};",
+            )],
+        );
+        assert_eq!(
+            r.resolve("crate::a", "Other").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(
+            r.resolve("crate::a", "b::Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(
+            r.resolve("crate::a", "helper").as_deref(),
+            Some("crate::b::helper")
+        );
+        assert_eq!(r.resolve("crate::a", "Thing"), None);
+    }
+
+    #[test]
+    fn follows_glob_imports() {
+        let r = resolver(ITEMS, &[("crate::a", "use crate::b::*;")]);
+        assert_eq!(
+            r.resolve("crate::a", "Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(r.resolve("crate::a", "Missing"), None);
+    }
+
+    #[test]
+    fn resolves_relative_paths() {
+        let r = resolver(ITEMS, &[]);
+        assert_eq!(
+            r.resolve("crate::a", "super::b::Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(
+            r.resolve("crate::b::inner", "super::super::b::Thing")
+                .as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(
+            r.resolve("crate::b", "self::Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(
+            r.resolve("crate::b::inner", "crate::b::Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+    }
+
+    #[test]
+    fn follows_reexports() {
+        let mut r = resolver(ITEMS, &[("crate::b", "pub use inner::Deep;")]);
+        r.add_public_module("crate::b".to_string());
+        assert_eq!(
+            r.resolve("crate::a", "crate::b::Deep").as_deref(),
+            Some("crate::b::inner::Deep")
+        );
+        assert!(r.is_exported("crate::b::Thing"));
+        assert!(r.is_exported("crate::b::inner::Deep"));
+        assert!(!r.is_exported("crate::b::inner::Hidden"));
+    }
+
+    #[test]
+    fn stops_at_glob_cycles() {
+        let r = resolver(
+            ITEMS,
+            &[
+                ("crate::a", "pub use crate::b::*;"),
+                ("crate::b", "pub use crate::a::*;"),
+            ],
+        );
+        assert_eq!(
+            r.resolve("crate::a", "Thing").as_deref(),
+            Some("crate::b::Thing")
+        );
+        assert_eq!(r.resolve("crate::a", "Missing"), None);
+        assert!(!r.is_exported("crate::a::Missing"));
+    }
+
+    #[test]
+    fn binary_roots_are_crates_of_their_own() {
+        let mut r = resolver(
+            &[
+                ("crate::Lib", ItemKind::Type),
+                ("crate::main", ItemKind::Module),
+                ("crate::main::Cli", ItemKind::Type),
+            ],
+            &[],
+        );
+        let aliases = BTreeMap::from([("my_crate".to_string(), CRATE_ROOT.to_string())]);
+        r.add_binary_root("crate::main".to_string(), aliases);
+        assert_eq!(
+            r.resolve("crate::main", "crate::Cli").as_deref(),
+            Some("crate::main::Cli")
+        );
+        assert_eq!(
+            r.resolve("crate::main", "my_crate::Lib").as_deref(),
+            Some("crate::Lib")
+        );
+        assert!(r.is_exported("crate::Lib"));
+        assert!(!r.is_exported("crate::main::Cli"));
+    }
+}
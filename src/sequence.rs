@@ -0,0 +1,289 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+
+use crate::parse::{FunctionInfo, ParsedProject, Step};
+use crate::resolve::short_name;
+
+/// Longest `alt` / `loop` label kept before truncating.
+const MAX_LABEL_LEN: usize = 48;
+
+/// A lifeline: the type owning a method, or the module of a free function.
+#[derive(Clone)]
+struct Participant {
+    id: String,
+    label: String,
+}
+
+struct Sequence<'a> {
+    functions: HashMap<&'a str, (&'a FunctionInfo, Participant)>,
+    participants: Vec<Participant>,
+    /// Functions currently being expanded, to stop at recursion.
+    stack: Vec<&'a str>,
+    max_depth: usize,
+}
+
+/// Render a Mermaid `sequenceDiagram` body following the calls made by
+/// `entry` (a full or `::`-suffix function path) up to `max_depth` levels.
+pub fn render_sequence(project: &ParsedProject, entry: &str, max_depth: usize) -> Result<String> {
+    let mut functions = HashMap::new();
+    for f in project
+        .file_functions_main
+        .values()
+        .chain(project.file_functions_tests.values())
+        .flatten()
+    {
+        let participant = Participant {
+            id: participant_id(&f.module),
+            label: short_name(&f.module).to_string(),
+        };
+        functions.insert(f.path.as_str(), (f, participant));
+    }
+    for class in project.classes.values() {
+        let participant = Participant {
+            id: participant_id(&class.path),
+            label: class.name.clone(),
+        };
+        for m in &class.methods {
+            functions.insert(m.path.as_str(), (m, participant.clone()));
+        }
+    }
+
+    let suffix = format!("::{entry}");
+    let mut candidates: Vec<&str> = functions
+        .keys()
+        .copied()
+        .filter(|path| *path == entry || path.ends_with(&suffix))
+        .collect();
+    candidates.sort();
+    let root = match candidates.as_slice() {
+        [] => bail!("no function matches `{entry}`"),
+        [only] => *only,
+        many => bail!("`{entry}` is ambiguous: {}", many.join(", ")),
+    };
+
+    let mut sequence = Sequence {
+        functions,
+        participants: Vec::new(),
+        stack: vec![root],
+        max_depth,
+    };
+
+    let (f, participant) = sequence.functions[root].clone();
+    sequence.participant(&participant);
+    let mut body = format!("    Note over {}: {}()\n", participant.id, f.name);
+    body.push_str(&sequence.steps(&participant, &f.body, 1, 1));
+
+    let mut out = String::from("sequenceDiagram\n");
+    for p in &sequence.participants {
+        out.push_str(&format!("    participant {} as {}\n", p.id, p.label));
+    }
+    out.push_str(&body);
+    Ok(out)
+}
+
+impl<'a> Sequence<'a> {
+    fn participant(&mut self, participant: &Participant) {
+        if !self.participants.iter().any(|p| p.id == participant.id) {
+            self.participants.push(participant.clone());
+        }
+    }
+
+    /// Messages for `steps` executed by `caller`; empty when nothing resolves.
+    fn steps(
+        &mut self,
+        caller: &Participant,
+        steps: &'a [Step],
+        depth: usize,
+        indent: usize,
+    ) -> String {
+        let pad = "    ".repeat(indent);
+        let mut out = String::new();
+
+        for step in steps {
+            match step {
+                Step::Call(call) => {
+                    let Some(target) = call.target.as_deref() else {
+                        continue;
+                    };
+                    let Some((f, callee)) = self.functions.get(target).cloned() else {
+                        continue;
+                    };
+                    self.participant(&callee);
+
+                    out.push_str(&format!(
+                        "{pad}{}->>+{}: {}()\n",
+                        caller.id, callee.id, f.name
+                    ));
+                    if depth < self.max_depth && !self.stack.contains(&target) {
+                        self.stack.push(target);
+                        out.push_str(&self.steps(&callee, &f.body, depth + 1, indent));
+                        self.stack.pop();
+                    }
+                    out.push_str(&format!(
+                        "{pad}{}-->>-{}: {}\n",
+                        callee.id,
+                        caller.id,
+                        escape(f.ret.as_deref().unwrap_or("()"))
+                    ));
+                }
+                Step::Branch(branches) => {
+                    let rendered: Vec<(&String, String)> = branches
+                        .iter()
+                        .map(|(label, steps)| (label, self.steps(caller, steps, depth, indent + 1)))
+                        .collect();
+                    if rendered.iter().all(|(_, body)| body.is_empty()) {
+                        continue;
+                    }
+
+                    let keyword = if rendered.len() == 1 { "opt" } else { "alt" };
+                    for (i, (label, body)) in rendered.iter().enumerate() {
+                        let keyword = if i == 0 { keyword } else { "else" };
+                        out.push_str(&format!("{pad}{keyword} {}\n", block_label(label)));
+                        out.push_str(body);
+                    }
+                    out.push_str(&format!("{pad}end\n"));
+                }
+                Step::Loop(label, steps) => {
+                    let body = self.steps(caller, steps, depth, indent + 1);
+                    if body.is_empty() {
+                        continue;
+                    }
+                    out.push_str(&format!("{pad}loop {}\n", block_label(label)));
+                    out.push_str(&body);
+                    out.push_str(&format!("{pad}end\n"));
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// Mermaid participant id for a module or type path.
+fn participant_id(path: &str) -> String {
+    path.replace("::", "__")
+}
+
+/// Strip characters Mermaid treats as statement syntax and shorten long
+/// conditions.
+fn block_label(label: &str) -> String {
+    let clean: String = label
+        .chars()
+        .filter(|c| !matches!(c, ';' | '#' | '\n'))
+        .collect();
+    if clean.chars().count() > MAX_LABEL_LEN {
+        let short: String = clean.chars().take(MAX_LABEL_LEN).collect();
+        escape(&format!("{}...", short.trim_end()))
+    } else {
+        escape(&clean)
+    }
+}
+
+/// Message text with `<`, `>` and `;` written as Mermaid entity codes, so
+/// generics are not swallowed as HTML tags.
+fn escape(text: &str) -> String {
+    text.replace(';', "#59;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::parse_project;
+
+    const LIB: &str = "pub struct Engine;
+        impl Engine {
+            pub fn start(&self, n: u8) -> bool {
+                if n > 0 { self.spin(); }
+                if n > 1 { self.spin(); } else { self.stop(); }
+                for _ in 0..n { match n { 0 => {} _ => self.stop() } }
+                true
+            }
+            fn spin(&self) { tick(); }
+            fn stop(&self) {}
+        }
+        pub fn tick() { tock(); }
+        pub fn tock() { tick(); }
+        mod a { pub fn run() {} }
+        mod b { pub fn run() {} }";
+
+    fn project(test: &str) -> ParsedProject {
+        let dir = std::env::temp_dir().join(format!("rust-to-mermaid-sequence-{test}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.rs"), LIB).unwrap();
+        parse_project(&dir).unwrap()
+    }
+
+    #[test]
+    fn nests_opt_alt_and_loop_blocks() {
+        let sequence = render_sequence(&project("blocks"), "Engine::start", 1).unwrap();
+        assert_eq!(
+            sequence,
+            "sequenceDiagram
+    participant crate__Engine as Engine
+    Note over crate__Engine: start()
+    opt n #gt; 0
+        crate__Engine->>+crate__Engine: spin()
+        crate__Engine-->>-crate__Engine: ()
+    end
+    alt n #gt; 1
+        crate__Engine->>+crate__Engine: spin()
+        crate__Engine-->>-crate__Engine: ()
+    else 
+        crate__Engine->>+crate__Engine: stop()
+        crate__Engine-->>-crate__Engine: ()
+    end
+    loop for _ in 0..n
+        alt 0
+        else _
+            crate__Engine->>+crate__Engine: stop()
+            crate__Engine-->>-crate__Engine: ()
+        end
+    end
+"
+        );
+    }
+
+    #[test]
+    fn follows_calls_down_to_the_depth_limit() {
+        let project = project("depth");
+        let shallow = render_sequence(&project, "spin", 1).unwrap();
+        assert!(shallow.contains("crate__Engine->>+crate: tick()"));
+        assert!(!shallow.contains("tock()"));
+
+        let deep = render_sequence(&project, "spin", 2).unwrap();
+        assert!(deep.contains("    participant crate as crate\n"));
+        assert!(deep.contains("crate->>+crate: tock()"));
+    }
+
+    #[test]
+    fn stops_at_recursion() {
+        let sequence = render_sequence(&project("recursion"), "tick", 10).unwrap();
+        assert_eq!(
+            sequence,
+            "sequenceDiagram
+    participant crate as crate
+    Note over crate: tick()
+    crate->>+crate: tock()
+    crate->>+crate: tick()
+    crate-->>-crate: ()
+    crate-->>-crate: ()
+"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_ambiguous_entries() {
+        let project = project("entries");
+        let unknown = render_sequence(&project, "nothing", 3).unwrap_err();
+        assert_eq!(unknown.to_string(), "no function matches `nothing`");
+        let ambiguous = render_sequence(&project, "run", 3).unwrap_err();
+        assert_eq!(
+            ambiguous.to_string(),
+            "`run` is ambiguous: crate::a::run, crate::b::run"
+        );
+        assert!(render_sequence(&project, "a::run", 3).is_ok());
+    }
+}
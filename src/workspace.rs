@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// One `[workspace] members` crate.
+#[derive(Debug, Clone)]
+pub struct Member {
+    /// Crate name as written in code (`my-crate` -> `my_crate`).
+    pub name: String,
+    /// Source directory, or the root file set by `[lib] path`.
+    pub src_dir: PathBuf,
+    /// Other members this crate lists under `[dependencies]`, by crate name.
+    pub dependencies: BTreeSet<String>,
+    /// Dependency keys renaming a member (`core = { package = "core-lib" }`),
+    /// as written in code -> that member's crate name.
+    pub renamed: BTreeMap<String, String>,
+}
+
+/// Read the members of the workspace whose root `Cargo.toml` is `root`
+/// (the manifest itself or its directory).
+///
+/// Member entries may end in `/*` to include every crate directory below
+/// them; a root `[package]` counts as a member too.
+pub fn load_workspace(root: &Path) -> Result<Vec<Member>> {
+    let (root_dir, manifest_path) = if root.is_dir() {
+        (root.to_path_buf(), root.join("Cargo.toml"))
+    } else {
+        (
+            root.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            root.to_path_buf(),
+        )
+    };
+    let manifest = read_manifest(&manifest_path)?;
+
+    let workspace = manifest
+        .get("workspace")
+        .and_then(Value::as_table)
+        .with_context(|| format!("{} has no [workspace] table", manifest_path.display()))?;
+    let excluded: BTreeSet<PathBuf> = strings(workspace.get("exclude"))
+        .map(|p| root_dir.join(p))
+        .collect();
+
+    let mut dirs = Vec::new();
+    if manifest.contains_key("package") {
+        dirs.push(root_dir.clone());
+    }
+    for pattern in strings(workspace.get("members")) {
+        match pattern.strip_suffix("/*") {
+            Some(parent) => {
+                let mut found: Vec<PathBuf> = fs::read_dir(root_dir.join(parent))
+                    .with_context(|| format!("reading workspace members `{pattern}`"))?
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.join("Cargo.toml").is_file())
+                    .collect();
+                found.sort();
+                dirs.extend(found);
+            }
+            None => dirs.push(root_dir.join(pattern)),
+        }
+    }
+    dirs.retain(|d| !excluded.contains(d));
+
+    let mut members = Vec::new();
+    // Dependencies can only be filtered to members once every name is known.
+    let mut package_deps = Vec::new();
+    let mut package_names = Vec::new();
+    for dir in dirs {
+        let manifest = read_manifest(&dir.join("Cargo.toml"))?;
+        let Some(package) = manifest
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(Value::as_str)
+        else {
+            continue;
+        };
+
+        let lib_path = manifest
+            .get("lib")
+            .and_then(|l| l.get("path"))
+            .and_then(Value::as_str);
+        let src_dir = match lib_path {
+            Some(path) => dir.join(path),
+            None => dir.join("src"),
+        };
+
+        // A renamed dependency (`alias = { package = "real" }`) still points
+        // at the `real` package.
+        let deps: Vec<(String, String)> = manifest
+            .get("dependencies")
+            .and_then(Value::as_table)
+            .into_iter()
+            .flatten()
+            .map(|(key, spec)| {
+                let package = spec.get("package").and_then(Value::as_str).unwrap_or(key);
+                (crate_name(key), package.to_string())
+            })
+            .collect();
+
+        package_names.push(package.to_string());
+        package_deps.push(deps);
+        members.push(Member {
+            name: crate_name(package),
+            src_dir,
+            dependencies: BTreeSet::new(),
+            renamed: BTreeMap::new(),
+        });
+    }
+
+    for (member, deps) in members.iter_mut().zip(package_deps) {
+        for (key, package) in deps.iter().filter(|(_, p)| package_names.contains(p)) {
+            let name = crate_name(package);
+            if *key != name {
+                member.renamed.insert(key.clone(), name.clone());
+            }
+            member.dependencies.insert(name);
+        }
+    }
+
+    Ok(members)
+}
+
+/// Crate name of the package whose sources are `src` (a directory or root
+/// file), from the nearest `Cargo.toml` above it with a `[package]`.
+pub fn package_name(src: &Path) -> Option<String> {
+    src.ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .filter(|manifest| manifest.is_file())
+        .find_map(|manifest| {
+            let manifest = read_manifest(&manifest).ok()?;
+            let name = manifest.get("package")?.get("name")?.as_str()?;
+            Some(crate_name(name))
+        })
+}
+
+fn read_manifest(path: &Path) -> Result<Table> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    text.parse::<Table>()
+        .with_context(|| format!("parsing {}", path.display()))
+}
+
+/// The string entries of a TOML array, if `value` is one.
+fn strings(value: Option<&Value>) -> impl Iterator<Item = &str> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Name a package is referred to by in code.
+fn crate_name(package: &str) -> String {
+    package.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `files` (relative path -> content) into a fresh directory named
+    /// after the test.
+    fn workspace_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-to-mermaid-workspace-{test}"));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn reads_members_globs_and_excludes() {
+        let dir = workspace_dir(
+            "members",
+            &[
+                (
+                    "Cargo.toml",
+                    "[package]\nname = \"root-app\"\n\
+                     [workspace]\nmembers = [\"crates/*\", \"tools\"]\n\
+                     exclude = [\"crates/skipped\"]\n",
+                ),
+                (
+                    "crates/core-lib/Cargo.toml",
+                    "[package]\nname = \"core-lib\"\n",
+                ),
+                (
+                    "crates/skipped/Cargo.toml",
+                    "[package]\nname = \"skipped\"\n",
+                ),
+                ("crates/notes/README.md", ""),
+                (
+                    "crates/util/Cargo.toml",
+                    "[package]\nname = \"util\"\n[lib]\npath = \"src/util.rs\"\n",
+                ),
+                ("tools/Cargo.toml", "[package]\nname = \"tools\"\n"),
+            ],
+        );
+        let members = load_workspace(&dir).unwrap();
+        let found: Vec<(&str, PathBuf)> = members
+            .iter()
+            .map(|m| {
+                (
+                    m.name.as_str(),
+                    m.src_dir.strip_prefix(&dir).unwrap().into(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("root_app", PathBuf::from("src")),
+                ("core_lib", PathBuf::from("crates/core-lib/src")),
+                ("util", PathBuf::from("crates/util/src/util.rs")),
+                ("tools", PathBuf::from("tools/src")),
+            ]
+        );
+        assert_eq!(
+            load_workspace(&dir.join("Cargo.toml")).unwrap().len(),
+            members.len()
+        );
+    }
+
+    #[test]
+    fn keeps_member_dependencies_and_renames() {
+        let dir = workspace_dir(
+            "dependencies",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"core-lib\", \"app\"]\n",
+                ),
+                ("core-lib/Cargo.toml", "[package]\nname = \"core-lib\"\n"),
+                (
+                    "app/Cargo.toml",
+                    "[package]\nname = \"app\"\n[dependencies]\n\
+                     core = { package = \"core-lib\", path = \"../core-lib\" }\n\
+                     core-lib = { path = \"../core-lib\" }\n\
+                     serde = \"1\"\n",
+                ),
+            ],
+        );
+        let members = load_workspace(&dir).unwrap();
+        let app = members.iter().find(|m| m.name == "app").unwrap();
+        assert_eq!(app.dependencies, BTreeSet::from(["core_lib".to_string()]));
+        assert_eq!(
+            app.renamed,
+            BTreeMap::from([("core".to_string(), "core_lib".to_string())])
+        );
+        let core = members.iter().find(|m| m.name == "core_lib").unwrap();
+        assert!(core.dependencies.is_empty());
+    }
+
+    #[test]
+    fn rejects_manifests_without_a_workspace() {
+        let dir = workspace_dir(
+            "no-workspace",
+            &[("Cargo.toml", "[package]\nname = \"a\"\n")],
+        );
+        let error = load_workspace(&dir).unwrap_err();
+        assert!(error.to_string().ends_with("has no [workspace] table"));
+    }
+}